@@ -1,36 +1,39 @@
 // ========= First, define your user data types =============
 
-use eframe::{
-    egui::{self, DragValue, TextStyle},
-    CreationContext,
-};
-use egui::{ahash::{HashMap, HashMapExt}, Pos2};
-use serde_json::{json, Result, Value};
+#[cfg(not(feature = "bevy"))]
+use eframe::CreationContext;
+use serde_json::json;
 use std::borrow::Cow;
 
+use crate::commands::{self, CommandHistory};
+use crate::eval_state::EvalState;
+use crate::profiler::Profiler;
+use crate::widget_spec;
 use crate::graph_ui::{
     editor_ui::NodeResponse,
+    egui_compat::egui,
     graph::{Graph, InputParamKind},
     id_type::{NodeId, OutputId},
     traits::{
-        DataTypeTrait, NodeDataTrait, NodeTemplateIter, NodeTemplateTrait, UserResponseTrait,
-        WidgetValueTrait,
+        DataTypeTrait, NodeDataTrait, NodeStyle, NodeTemplateIter, NodeTemplateTrait,
+        UserResponseTrait, WidgetValueTrait,
     },
     ui_state::GraphEditorState,
 };
+use egui::{ahash::HashMap, DragValue, Pos2, TextStyle};
 
 /// The NodeData holds a custom data struct inside each node. It's useful to
 /// store additional information that doesn't live in parameters. For this
 /// example, the node data stores the template (i.e. the "type") of the node.
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct MyNodeData {
-    template: MyNodeTemplate,
+    pub(crate) template: MyNodeTemplate,
 }
 
 /// `DataType`s are what defines the possible range of connections when
 /// attaching two ports together. The graph UI will make sure to not allow
 /// attaching incompatible datatypes.
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum MyDataType {
     Scalar,
@@ -39,6 +42,12 @@ pub enum MyDataType {
     Text
 }
 
+impl std::fmt::Display for MyDataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 /// In the graph, input parameters can optionally have a constant value. This
 /// value can be directly edited in a widget inside the node itself.
 ///
@@ -87,12 +96,55 @@ impl MyValueType {
             anyhow::bail!("Invalid cast from {:?} to scalar", self)
         }
     }
+
+    /// The `MyDataType` this value is an instance of.
+    fn data_type(&self) -> MyDataType {
+        match self {
+            MyValueType::Vec2 { .. } => MyDataType::Vec2,
+            MyValueType::Scalar { .. } => MyDataType::Scalar,
+            MyValueType::Widget { .. } => MyDataType::Widget,
+            MyValueType::Text { .. } => MyDataType::Text,
+        }
+    }
+}
+
+/// A port received a value whose type doesn't match what it expects, and no
+/// implicit coercion (see [`coerce_value`]) applies either. Propagated
+/// through `anyhow` so the active-node result text in `update` can show the
+/// concrete mismatch instead of a magic error code.
+#[derive(Debug, thiserror::Error)]
+#[error("input '{input}' expected {expected}, found {found}")]
+pub struct TypeMismatch {
+    pub expected: MyDataType,
+    pub found: MyDataType,
+    pub input: String,
+}
+
+/// Declares which `MyValueType` shapes may stand in for another port's
+/// expected type without the user needing an explicit conversion node, e.g. a
+/// scalar wired into a text input. Returns `None` when no such rule exists,
+/// in which case the caller should report a [`TypeMismatch`] rather than
+/// silently dropping the value.
+fn coerce_value(value: MyValueType, target: MyDataType) -> Option<MyValueType> {
+    match (value, target) {
+        (MyValueType::Scalar { value }, MyDataType::Text) => {
+            Some(MyValueType::Text { value: value.to_string() })
+        }
+        (MyValueType::Text { value }, MyDataType::Widget) => {
+            Some(MyValueType::Widget { value: json!({ "text": value }) })
+        }
+        _ => None,
+    }
 }
 
 /// NodeTemplate is a mechanism to define node templates. It's what the graph
 /// will display in the "new node" popup. The user code needs to tell the
 /// library how to convert a NodeTemplate into a Node.
-#[derive(Clone, Copy)]
+///
+/// Most kinds are fixed, compile-time variants, but `Dynamic` lets node
+/// shapes be produced at runtime from a [`DynamicTemplate`] (e.g. one input
+/// per axis reported by a device that's only enumerated at startup).
+#[derive(Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum MyNodeTemplate {
     MakeScalar,
@@ -103,7 +155,36 @@ pub enum MyNodeTemplate {
     SubtractVector,
     VectorTimesScalar,
     Scaffold,
-    Text
+    Text,
+    Dynamic(std::rc::Rc<DynamicTemplate>),
+}
+
+/// A single port to create when building a node from a [`DynamicTemplate`].
+#[derive(Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum PortDescriptor {
+    /// A constant-editable scalar input with a bounded range, e.g. one per
+    /// axis on a discovered input device. The range isn't enforced by the
+    /// graph itself yet; it's there for the inline widget to clamp against.
+    Slider { name: String, min: f32, max: f32, default: f32 },
+    /// A scalar output representing a momentary digital signal.
+    Button { name: String },
+    /// A plain connection-only scalar input.
+    ScalarIn { name: String },
+    /// A widget-typed output.
+    WidgetOut { name: String },
+}
+
+/// A node template whose ports are only known at runtime. Building one
+/// iterates `ports` and calls `add_input_param`/`add_output_param`
+/// accordingly, instead of the fixed set of calls a compile-time
+/// `MyNodeTemplate` variant makes in `build_node`.
+#[derive(Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct DynamicTemplate {
+    pub label: String,
+    pub categories: Vec<String>,
+    pub ports: Vec<PortDescriptor>,
 }
 
 /// The response type is used to encode side-effects produced when drawing a
@@ -155,34 +236,36 @@ impl NodeTemplateTrait for MyNodeTemplate {
     type DataType = MyDataType;
     type ValueType = MyValueType;
     type UserState = MyGraphState;
-    type CategoryType = &'static str;
+    type CategoryType = String;
 
     fn node_finder_label(&self, _user_state: &mut Self::UserState) -> Cow<'_, str> {
-        Cow::Borrowed(match self {
-            MyNodeTemplate::MakeScalar => "New scalar",
-            MyNodeTemplate::AddScalar => "Scalar add",
-            MyNodeTemplate::SubtractScalar => "Scalar subtract",
-            MyNodeTemplate::MakeVector => "New vector",
-            MyNodeTemplate::AddVector => "Vector add",
-            MyNodeTemplate::SubtractVector => "Vector subtract",
-            MyNodeTemplate::VectorTimesScalar => "Vector times scalar",
-            MyNodeTemplate::Scaffold => "Scaffold2",
-            MyNodeTemplate::Text => "Text",
-        })
+        match self {
+            MyNodeTemplate::MakeScalar => Cow::Borrowed("New scalar"),
+            MyNodeTemplate::AddScalar => Cow::Borrowed("Scalar add"),
+            MyNodeTemplate::SubtractScalar => Cow::Borrowed("Scalar subtract"),
+            MyNodeTemplate::MakeVector => Cow::Borrowed("New vector"),
+            MyNodeTemplate::AddVector => Cow::Borrowed("Vector add"),
+            MyNodeTemplate::SubtractVector => Cow::Borrowed("Vector subtract"),
+            MyNodeTemplate::VectorTimesScalar => Cow::Borrowed("Vector times scalar"),
+            MyNodeTemplate::Scaffold => Cow::Borrowed("Scaffold2"),
+            MyNodeTemplate::Text => Cow::Borrowed("Text"),
+            MyNodeTemplate::Dynamic(template) => Cow::Owned(template.label.clone()),
+        }
     }
 
     // this is what allows the library to show collapsible lists in the node finder.
-    fn node_finder_categories(&self, _user_state: &mut Self::UserState) -> Vec<&'static str> {
+    fn node_finder_categories(&self, _user_state: &mut Self::UserState) -> Vec<String> {
         match self {
             MyNodeTemplate::MakeScalar
             | MyNodeTemplate::AddScalar
-            | MyNodeTemplate::SubtractScalar => vec!["Scalar"],
+            | MyNodeTemplate::SubtractScalar => vec!["Scalar".to_string()],
             MyNodeTemplate::MakeVector
             | MyNodeTemplate::AddVector
-            | MyNodeTemplate::SubtractVector => vec!["Vector"],
-            MyNodeTemplate::VectorTimesScalar => vec!["Vector", "Scalar"],
-            MyNodeTemplate::Scaffold => vec!["Widget"],
-            MyNodeTemplate::Text => vec!["WGT"],
+            | MyNodeTemplate::SubtractVector => vec!["Vector".to_string()],
+            MyNodeTemplate::VectorTimesScalar => vec!["Vector".to_string(), "Scalar".to_string()],
+            MyNodeTemplate::Scaffold => vec!["Widget".to_string()],
+            MyNodeTemplate::Text => vec!["WGT".to_string()],
+            MyNodeTemplate::Dynamic(template) => template.categories.clone(),
         }
     }
 
@@ -193,7 +276,7 @@ impl NodeTemplateTrait for MyNodeTemplate {
     }
 
     fn user_data(&self, _user_state: &mut Self::UserState) -> Self::NodeData {
-        MyNodeData { template: *self }
+        MyNodeData { template: self.clone() }
     }
 
     fn build_node(
@@ -239,7 +322,21 @@ impl NodeTemplateTrait for MyNodeTemplate {
                 true,
             );
         };
-        let input_text = |graph: &mut MyGraph, name: &str| {
+        // Unlike `input_widget`, this port is allowed to stay disconnected;
+        // evaluation treats an empty one as `None` instead of falling back to
+        // the `json!({})` constant, and it has no inline widget since there's
+        // nothing useful to edit when it's meant to be left empty.
+        let input_widget_optional = |graph: &mut MyGraph, name: &str| {
+            graph.add_optional_input_param(
+                node_id,
+                name.to_string(),
+                MyDataType::Widget,
+                MyValueType::Widget { value: json!({}) },
+                InputParamKind::ConnectionOnly,
+                false,
+            );
+        };
+        let _input_text = |graph: &mut MyGraph, name: &str| {
             graph.add_input_param(
                 node_id,
                 name.to_string(),
@@ -312,7 +409,7 @@ impl NodeTemplateTrait for MyNodeTemplate {
             }
             MyNodeTemplate::Scaffold => {
                 input_widget(graph, "body");
-                input_widget(graph, "header");
+                input_widget_optional(graph, "header");
                 output_widget(graph, "widget");
             }
             MyNodeTemplate::Text =>  {
@@ -334,11 +431,50 @@ impl NodeTemplateTrait for MyNodeTemplate {
                 );
                 output_widget(graph, "widget");
             },
+            MyNodeTemplate::Dynamic(template) => {
+                for port in &template.ports {
+                    match port {
+                        PortDescriptor::Slider { name, default, .. } => {
+                            graph.add_input_param(
+                                node_id,
+                                name.clone(),
+                                MyDataType::Scalar,
+                                MyValueType::Scalar { value: *default },
+                                InputParamKind::ConstantOnly,
+                                true,
+                            );
+                        }
+                        PortDescriptor::Button { name } => {
+                            output_scalar(graph, name);
+                        }
+                        PortDescriptor::ScalarIn { name } => {
+                            graph.add_input_param(
+                                node_id,
+                                name.clone(),
+                                MyDataType::Scalar,
+                                MyValueType::Scalar { value: 0.0 },
+                                InputParamKind::ConnectionOnly,
+                                false,
+                            );
+                        }
+                        PortDescriptor::WidgetOut { name } => {
+                            output_widget(graph, name);
+                        }
+                    }
+                }
+            }
         }
     }
 }
 
-pub struct AllMyNodeTemplates;
+/// Enumerates every node kind the node finder should offer: the fixed
+/// compile-time templates, plus one `Dynamic` template per runtime
+/// descriptor in `dynamic` (e.g. one per input device discovered this
+/// session).
+pub struct AllMyNodeTemplates {
+    pub dynamic: Vec<DynamicTemplate>,
+}
+
 impl NodeTemplateIter for AllMyNodeTemplates {
     type Item = MyNodeTemplate;
 
@@ -346,7 +482,7 @@ impl NodeTemplateIter for AllMyNodeTemplates {
         // This function must return a list of node kinds, which the node finder
         // will use to display it to the user. Crates like strum can reduce the
         // boilerplate in enumerating all variants of an enum.
-        vec![
+        let mut kinds = vec![
             MyNodeTemplate::MakeScalar,
             MyNodeTemplate::MakeVector,
             MyNodeTemplate::AddScalar,
@@ -355,8 +491,15 @@ impl NodeTemplateIter for AllMyNodeTemplates {
             MyNodeTemplate::SubtractVector,
             MyNodeTemplate::VectorTimesScalar,
             MyNodeTemplate::Scaffold,
-            MyNodeTemplate::Text
-        ]
+            MyNodeTemplate::Text,
+        ];
+        kinds.extend(
+            self.dynamic
+                .iter()
+                .cloned()
+                .map(|template| MyNodeTemplate::Dynamic(std::rc::Rc::new(template))),
+        );
+        kinds
     }
 }
 
@@ -391,8 +534,9 @@ impl WidgetValueTrait for MyValueType {
                 });
             }
             MyValueType::Widget { value } => {
-                ui.horizontal(|ui| {
+                ui.vertical(|ui| {
                     ui.label(param_name);
+                    widget_spec::draw_widget_spec(ui, value);
                 });
             }
             MyValueType::Text { value } => {
@@ -461,71 +605,118 @@ impl NodeDataTrait for MyNodeData {
 
         responses
     }
-}
-
-type MyGraph = Graph<MyNodeData, MyDataType, MyValueType>;
-type MyEditorState =
-    GraphEditorState<MyNodeData, MyDataType, MyValueType, MyNodeTemplate, MyGraphState>;
-
-#[derive(Default)]
-pub struct NodeGraphExample {
-    // The `GraphEditorState` is the top-level object. You "register" all your
-    // custom types by specifying it as its generic parameters.
-    state: MyEditorState,
 
-    user_state: MyGraphState,
-}
+    // Nodes are grouped visually the same way they're grouped in the node
+    // finder (Scalar / Vector / Widget), so a glance at the titlebar color
+    // tells you a node's category without reading its label.
+    fn node_style(&self, _user_state: &mut Self::UserState) -> NodeStyle {
+        let accent = match &self.template {
+            MyNodeTemplate::MakeScalar | MyNodeTemplate::AddScalar | MyNodeTemplate::SubtractScalar => {
+                egui::Color32::from_rgb(38, 109, 211)
+            }
+            MyNodeTemplate::MakeVector
+            | MyNodeTemplate::AddVector
+            | MyNodeTemplate::SubtractVector
+            | MyNodeTemplate::VectorTimesScalar => egui::Color32::from_rgb(238, 207, 109),
+            MyNodeTemplate::Scaffold => egui::Color32::from_rgb(38, 255, 150),
+            MyNodeTemplate::Text => egui::Color32::from_rgb(124, 25, 180),
+            MyNodeTemplate::Dynamic(_) => egui::Color32::from_rgb(160, 160, 160),
+        };
 
-#[cfg(feature = "persistence")]
-const PERSISTENCE_KEY: &str = "egui_node_graph";
-
-#[cfg(feature = "persistence")]
-impl NodeGraphExample {
-    /// If the persistence feature is enabled, Called once before the first frame.
-    /// Load previous app state (if any).
-    pub fn new(cc: &CreationContext<'_>) -> Self {
-        let state = cc
-            .storage
-            .and_then(|storage| eframe::get_value(storage, PERSISTENCE_KEY))
-            .unwrap_or_default();
-        Self {
-            state,
-            user_state: MyGraphState::default(),
+        NodeStyle {
+            titlebar: Some(accent.linear_multiply(0.5)),
+            titlebar_hovered: Some(accent.linear_multiply(0.7)),
+            ..Default::default()
         }
     }
 }
-// #[cfg(feature = "persistence")]
-const PERSISTENCE_KEY: &str = "egui_node_graph";
 
-/// We derive Deserialize/Serialize so we can persist app state on shutdown.
+pub(crate) type MyGraph = Graph<MyNodeData, MyDataType, MyValueType>;
+
+/// Wrapped by [`crate::bevy_plugin::GraphResource`] for a host Bevy app, so
+/// this needs to be nameable outside the crate too.
+pub type MyEditorState =
+    GraphEditorState<MyNodeData, MyDataType, MyValueType, MyNodeTemplate, MyGraphState>;
+
+/// The standalone `eframe` desktop front end. Not built under the `bevy`
+/// feature: `MyEditorState`'s `egui::Ui` there resolves (through
+/// `egui_compat`) to `bevy_egui`'s copy of the crate, which isn't the same
+/// type `eframe::App`'s `egui::Context` needs — a Bevy host embeds the graph
+/// through `bevy_plugin::NodeGraphPlugin` instead, which has no use for this
+/// struct or `main.rs`'s `eframe::run_native` entry point.
 // #[derive(serde::Deserialize, serde::Serialize)]
 // #[serde(default)] // if we add new fields, give them default values when deserializing old state
-
+#[cfg(not(feature = "bevy"))]
 pub struct NagaApp {
-    // Example stuff:
-    label: String,
-
-    // #[serde(skip)] // This how you opt-out of serialization of a field
-    value: f32,
     state: MyEditorState,
 
     user_state: MyGraphState,
+
+    /// Undo/redo stack for structural graph edits (add/remove node,
+    /// connect/disconnect, move, param edits).
+    command_history: CommandHistory,
+    /// Previous frame's input values, used to detect param edits made by
+    /// dragging a widget so they can be turned into undoable `SetParam`
+    /// commands. See [`commands::record_param_edits`].
+    param_snapshot: commands::ParamSnapshot,
+    /// Node templates discovered at runtime rather than baked into
+    /// `MyNodeTemplate`, offered to the node finder alongside the fixed
+    /// kinds. See [`DynamicTemplate`].
+    dynamic_templates: Vec<DynamicTemplate>,
+    /// Memoized per-node evaluation results, persisted across frames with
+    /// dirty tracking so only what actually changed gets recomputed. See
+    /// [`EvalState`].
+    eval_state: EvalState,
+    /// Opt-in per-node timing/cache-hit stats from the most recent
+    /// evaluation pass. See [`Profiler`].
+    profiler: Profiler,
 }
 
+#[cfg(not(feature = "bevy"))]
 impl Default for NagaApp {
     fn default() -> Self {
         Self {
-            label: "".to_owned(),
-            value: 10.0,
             state: GraphEditorState::default(),
             user_state: MyGraphState::default(),
+            command_history: CommandHistory::default(),
+            param_snapshot: commands::ParamSnapshot::default(),
+            dynamic_templates: discover_dynamic_templates(),
+            eval_state: EvalState::default(),
+            profiler: Profiler::default(),
         }
     }
 }
 
+/// Stands in for scanning an external source (e.g. a force-feedback device's
+/// reported axes/buttons) and turning each capability into a port. A real
+/// integration would replace this with the actual enumeration call.
+pub(crate) fn discover_dynamic_templates() -> Vec<DynamicTemplate> {
+    vec![DynamicTemplate {
+        label: "Gamepad".to_string(),
+        categories: vec!["Device".to_string()],
+        ports: vec![
+            PortDescriptor::Slider {
+                name: "left_stick_x".to_string(),
+                min: -1.0,
+                max: 1.0,
+                default: 0.0,
+            },
+            PortDescriptor::Slider {
+                name: "left_stick_y".to_string(),
+                min: -1.0,
+                max: 1.0,
+                default: 0.0,
+            },
+            PortDescriptor::Button { name: "south".to_string() },
+            PortDescriptor::WidgetOut { name: "state".to_string() },
+        ],
+    }]
+}
+
+#[cfg(not(feature = "bevy"))]
 impl NagaApp {
     /// Called once before the first frame.
-    pub fn new(cc: &CreationContext<'_>) -> Self {
+    pub fn new(_cc: &CreationContext<'_>) -> Self {
         // This is also where you can customize the look and feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
@@ -537,11 +728,39 @@ impl NagaApp {
 
         Default::default()
     }
+
+    /// Undoes the last command and refreshes `param_snapshot` to match, so
+    /// the value the undo just restored isn't mistaken for a fresh edit and
+    /// re-recorded (which would also wipe out the redo entry just pushed).
+    fn undo(&mut self) {
+        self.command_history.undo(
+            &mut self.state.graph,
+            &mut self.state.node_order,
+            &mut self.state.node_positions,
+            &mut self.user_state,
+            &mut self.eval_state,
+        );
+        self.param_snapshot = commands::snapshot_params(&self.state.graph);
+    }
+
+    /// Redoes the last undone command; see [`NagaApp::undo`] for why the
+    /// param snapshot is refreshed alongside it.
+    fn redo(&mut self) {
+        self.command_history.redo(
+            &mut self.state.graph,
+            &mut self.state.node_order,
+            &mut self.state.node_positions,
+            &mut self.user_state,
+            &mut self.eval_state,
+        );
+        self.param_snapshot = commands::snapshot_params(&self.state.graph);
+    }
 }
 
+#[cfg(not(feature = "bevy"))]
 impl eframe::App for NagaApp {
     /// Called by the frame work to save state before shutdown.
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+    fn save(&mut self, _storage: &mut dyn eframe::Storage) {
         // eframe::set_value(storage, eframe::APP_KEY, self);
     }
 
@@ -550,6 +769,8 @@ impl eframe::App for NagaApp {
         // Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
 
+        let mut undo_requested = false;
+        let mut redo_requested = false;
         ctx.input(|i| {
             if i.key_down(egui::Key::ArrowLeft) {
                 self.state.pan_zoom.pan.x -= 10.0;
@@ -569,7 +790,25 @@ impl eframe::App for NagaApp {
             if i.key_down(egui::Key::Q) {
                 self.state.pan_zoom.zoom -= 10.0;
             }
+
+            let ctrl_or_cmd = i.modifiers.ctrl || i.modifiers.command;
+            if ctrl_or_cmd && i.key_pressed(egui::Key::Z) {
+                if i.modifiers.shift {
+                    redo_requested = true;
+                } else {
+                    undo_requested = true;
+                }
+            }
+            if ctrl_or_cmd && i.key_pressed(egui::Key::Y) {
+                redo_requested = true;
+            }
         });
+        if undo_requested {
+            self.undo();
+        }
+        if redo_requested {
+            self.redo();
+        }
 
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
@@ -582,85 +821,122 @@ impl eframe::App for NagaApp {
                         if ui.button("Quit").clicked() {
                             // ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
+                        if ui.button("Export graph as DOT").clicked() {
+                            println!("{}", crate::dot::export(&self.state.graph));
+                        }
                     });
                     ui.add_space(16.0);
                 }
 
+                if ui
+                    .add_enabled(self.command_history.can_undo(), egui::Button::new("⟲ Undo"))
+                    .clicked()
+                {
+                    self.undo();
+                }
+                if ui
+                    .add_enabled(self.command_history.can_redo(), egui::Button::new("⟳ Redo"))
+                    .clicked()
+                {
+                    self.redo();
+                }
+                ui.add_space(16.0);
+
+                let mut profiling_enabled = self.profiler.is_enabled();
+                if ui.checkbox(&mut profiling_enabled, "Profile evaluation").changed() {
+                    self.profiler.set_enabled(profiling_enabled);
+                }
+                ui.add_space(16.0);
+
                 egui::widgets::global_dark_light_mode_buttons(ui);
             });
         });
         egui::SidePanel::left("Menu").show(ctx, |ui| {
             ui.label("Sosiska");
             if  ui.button("add").clicked() {
-                    
-
+                    let text = commands::do_add_node(
+                        &mut self.command_history,
+                        &mut self.state.graph,
+                        &mut self.state.node_order,
+                        &mut self.state.node_positions,
+                        &mut self.user_state,
+                        MyNodeTemplate::Text,
+                        Pos2::new(0.0, 0.0),
+                    );
+
+                    let scaffold = commands::do_add_node(
+                        &mut self.command_history,
+                        &mut self.state.graph,
+                        &mut self.state.node_order,
+                        &mut self.state.node_positions,
+                        &mut self.user_state,
+                        MyNodeTemplate::Scaffold,
+                        Pos2::new(300.0, 0.0),
+                    );
 
-                    let text = self.state.graph.add_node(
-                        MyNodeTemplate::Text.node_graph_label(&mut self.user_state), 
-                        MyNodeTemplate::Text.user_data(&mut self.user_state), 
-                        |graph, node_id| {
-                            MyNodeTemplate::Text.build_node(graph, &mut self.user_state, node_id)
-                        });
-                        self.state.node_positions.insert(
-                            text,
-                            Pos2::new(0.0, 0.0),
-                                // + vec2(
-                                //     (n % 10) as f32 * 150.0,
-                                //     0.0 + (200 * (n / 10) as i32) as f32,
-                                // ),
-                        );
-                        self.state.node_order.push(text);
-
-
-                        let scaffold = self.state.graph.add_node(
-                            MyNodeTemplate::Scaffold.node_graph_label(&mut self.user_state), 
-                            MyNodeTemplate::Scaffold.user_data(&mut self.user_state), 
-                            |graph, node_id| {
-                                MyNodeTemplate::Scaffold.build_node(graph, &mut self.user_state, node_id)
-                            });
-                            self.state.node_positions.insert(
-                                scaffold,
-                                Pos2::new(300.0, 0.0),
-                                    // + vec2(
-                                    //     (n % 10) as f32 * 150.0,
-                                    //     0.0 + (200 * (n / 10) as i32) as f32,
-                                    // ),
-                            );
-                            self.state.node_order.push(scaffold);
                     let out = self.state.graph.nodes[text].get_output("widget").unwrap();
                     let inp = self.state.graph.nodes[scaffold].get_input("body").unwrap();
-                    self.state.graph.add_connection(out, inp);
-                    // delayed_responses.push(NodeResponse::CreatedNode(new_node));
-    
+                    commands::do_connect(
+                        &mut self.command_history,
+                        &mut self.state.graph,
+                        &mut self.eval_state,
+                        out,
+                        inp,
+                    )
+                    .unwrap();
             }
         });
         let graph_response = egui::CentralPanel::default()
             .show(ctx, |ui| {
                 self.state.draw_graph_editor(
                     ui,
-                    AllMyNodeTemplates,
+                    AllMyNodeTemplates {
+                        dynamic: self.dynamic_templates.clone(),
+                    },
                     &mut self.user_state,
                     Vec::default(),
                 )
             })
             .inner;
         for node_response in graph_response.node_responses {
-            // Here, we ignore all other graph events. But you may find
-            // some use for them. For example, by playing a sound when a new
-            // connection is created
-            if let NodeResponse::User(user_event) = node_response {
-                match user_event {
-                    MyResponse::SetActiveNode(node) => self.user_state.active_node = Some(node),
-                    MyResponse::ClearActiveNode => self.user_state.active_node = None,
-                }
-            }
+            commands::apply_node_response(
+                &mut self.command_history,
+                &mut self.state,
+                &mut self.eval_state,
+                &mut self.user_state,
+                node_response,
+            );
         }
 
+        self.param_snapshot = commands::record_param_edits(
+            &mut self.command_history,
+            &self.state.graph,
+            &mut self.eval_state,
+            &self.param_snapshot,
+        );
+
         if let Some(node) = self.user_state.active_node {
             if self.state.graph.nodes.contains_key(node) {
-                let text = match evaluate_node(&self.state.graph, node, &mut HashMap::new()) {
-                    Ok(value) => format!("The result is: {:?}", value),
-                    Err(err) => format!("Execution error: {}", err),
+                // Validate before evaluating: a required input left
+                // disconnected is a clearer error than whatever downstream
+                // failure results from evaluating it anyway.
+                let missing_input = self.state.graph[node].inputs.iter().find(|(_, input_id)| {
+                    !self.state.graph.is_input_satisfied(*input_id)
+                });
+                let text = if let Some((name, _)) = missing_input {
+                    format!("Missing required input: {}", name)
+                } else {
+                    match evaluate_subgraph(&self.state.graph, node, &mut self.eval_state, &mut self.profiler) {
+                        // The version counter is surfaced here so it's visible
+                        // whether a repaint actually recomputed this node or
+                        // just replayed its cached result from a prior frame.
+                        Ok(value) => format!(
+                            "The result is: {:?} (v{})",
+                            value,
+                            self.eval_state.version(node)
+                        ),
+                        Err(err) => format!("Execution error: {}", err),
+                    }
                 };
                 print!("{}", text);
                 ctx.debug_painter().text(
@@ -674,7 +950,30 @@ impl eframe::App for NagaApp {
                 self.user_state.active_node = None;
             }
         }
-        
+
+        if self.profiler.is_enabled() {
+            egui::SidePanel::right("profiler").show(ctx, |ui| {
+                ui.heading("Node profile (last pass)");
+                // `stats()` iterates a `HashMap` rebuilt fresh every pass, so
+                // ties in `self_time` (common for near-instant nodes) need a
+                // stable tiebreaker or the list would reshuffle every frame
+                // even when nothing actually changed.
+                let mut rows: Vec<_> = self
+                    .profiler
+                    .stats()
+                    .filter_map(|(node_id, stats)| {
+                        self.state.graph.nodes.get(node_id).map(|node| (node.label.clone(), stats))
+                    })
+                    .collect();
+                rows.sort_by(|a, b| b.1.self_time.cmp(&a.1.self_time).then_with(|| a.0.cmp(&b.0)));
+                for (label, stats) in rows {
+                    ui.label(format!(
+                        "{}: {:?} ({} calls, {} hit / {} miss)",
+                        label, stats.self_time, stats.invocations, stats.cache_hits, stats.cache_misses,
+                    ));
+                }
+            });
+        }
 
         // println!("{:?}", *x);
 
@@ -707,14 +1006,41 @@ impl eframe::App for NagaApp {
     }
 }
 
-type OutputsCache = HashMap<OutputId, MyValueType>;
+pub(crate) type OutputsCache = HashMap<OutputId, MyValueType>;
+
+/// Tracks nodes currently being evaluated further up the same recursive call
+/// chain, so a connection that loops back on itself is reported as an error
+/// instead of recursing forever.
+type VisitSet = std::collections::HashSet<NodeId>;
 
-/// Recursively evaluates all dependencies of this node, then evaluates the node itself.
-pub fn evaluate_node(
+/// Recursively evaluates all dependencies of this node, then evaluates the
+/// node itself — unless `eval_state` already holds a clean (non-dirty)
+/// result for every one of this node's outputs, in which case that cached
+/// result is returned untouched. See [`EvalState`].
+pub(crate) fn evaluate_node(
     graph: &MyGraph,
     node_id: NodeId,
-    outputs_cache: &mut OutputsCache,
+    eval_state: &mut EvalState,
+    visiting: &mut VisitSet,
+    profiler: &mut Profiler,
 ) -> anyhow::Result<MyValueType> {
+    let outputs = &graph[node_id].outputs;
+    if !eval_state.is_dirty(node_id) && !outputs.is_empty() {
+        let all_cached = outputs
+            .iter()
+            .all(|(_, output_id)| eval_state.outputs_cache.contains_key(output_id));
+        if all_cached {
+            profiler.record_cache_hit(node_id);
+            let (_, last_output) = outputs.last().expect("checked non-empty above");
+            return Ok(eval_state.outputs_cache.get(last_output).cloned().expect("checked cached above"));
+        }
+    }
+
+    if !visiting.insert(node_id) {
+        anyhow::bail!("Cycle detected in the graph: node depends on its own output");
+    }
+    profiler.begin_node(node_id);
+
     // To solve a similar problem as creating node types above, we define an
     // Evaluator as a convenience. It may be overkill for this small example,
     // but something like this makes the code much more readable when the
@@ -722,21 +1048,31 @@ pub fn evaluate_node(
 
     struct Evaluator<'a> {
         graph: &'a MyGraph,
-        outputs_cache: &'a mut OutputsCache,
+        eval_state: &'a mut EvalState,
+        visiting: &'a mut VisitSet,
+        profiler: &'a mut Profiler,
         node_id: NodeId,
     }
     impl<'a> Evaluator<'a> {
-        fn new(graph: &'a MyGraph, outputs_cache: &'a mut OutputsCache, node_id: NodeId) -> Self {
+        fn new(
+            graph: &'a MyGraph,
+            eval_state: &'a mut EvalState,
+            visiting: &'a mut VisitSet,
+            profiler: &'a mut Profiler,
+            node_id: NodeId,
+        ) -> Self {
             Self {
                 graph,
-                outputs_cache,
+                eval_state,
+                visiting,
+                profiler,
                 node_id,
             }
         }
         fn evaluate_input(&mut self, name: &str) -> anyhow::Result<MyValueType> {
             // Calling `evaluate_input` recursively evaluates other nodes in the
             // graph until the input value for a paramater has been computed.
-            evaluate_input(self.graph, self.node_id, name, self.outputs_cache)
+            evaluate_input(self.graph, self.node_id, name, self.eval_state, self.visiting, self.profiler)
         }
         fn populate_output(
             &mut self,
@@ -756,7 +1092,7 @@ pub fn evaluate_node(
             //
             // Note that this is just one possible semantic interpretation of
             // the graphs, you can come up with your own evaluation semantics!
-            populate_output(self.graph, self.outputs_cache, self.node_id, name, value)
+            populate_output(self.graph, self.eval_state, self.node_id, name, value)
         }
         fn input_vector(&mut self, name: &str) -> anyhow::Result<egui::Vec2> {
             self.evaluate_input(name)?.try_to_vec2()
@@ -765,28 +1101,44 @@ pub fn evaluate_node(
             self.evaluate_input(name)?.try_to_scalar()
         }
 
-        fn input_widget(&mut self, name: &str) -> anyhow::Result<serde_json::Value, i32> {
-            let x = self.evaluate_input(name);
-            match x {
-                Ok(value) => match value {
-                    MyValueType::Vec2 { value } => Err(1),
-                    MyValueType::Scalar { value } => Err(2),
-                    MyValueType::Widget { value } => Ok(value),
-                    MyValueType::Text { value } => Err(4),
+        fn input_widget(&mut self, name: &str) -> anyhow::Result<serde_json::Value> {
+            let value = self.evaluate_input(name)?;
+            let found = value.data_type();
+            match value {
+                MyValueType::Widget { value } => Ok(value),
+                other => match coerce_value(other, MyDataType::Widget) {
+                    Some(MyValueType::Widget { value }) => Ok(value),
+                    _ => Err(TypeMismatch { expected: MyDataType::Widget, found, input: name.to_string() }.into()),
+                },
+            }
+        }
+        /// Like `input_widget`, but for an optional port: an unconnected
+        /// input comes back as `Ok(None)` instead of silently evaluating to
+        /// the port's constant fallback value.
+        fn input_widget_optional(&mut self, name: &str) -> anyhow::Result<Option<serde_json::Value>> {
+            let input_id = self.graph[self.node_id].get_input(name)?;
+            if self.graph.connection(input_id).is_none() {
+                return Ok(None);
+            }
+            let value = self.evaluate_input(name)?;
+            let found = value.data_type();
+            match value {
+                MyValueType::Widget { value } => Ok(Some(value)),
+                other => match coerce_value(other, MyDataType::Widget) {
+                    Some(MyValueType::Widget { value }) => Ok(Some(value)),
+                    _ => Err(TypeMismatch { expected: MyDataType::Widget, found, input: name.to_string() }.into()),
                 },
-                Err(_) => Err(3),
             }
         }
-        fn input_text(&mut self, name: &str) -> anyhow::Result<String, i32> {
-            let x = self.evaluate_input(name);
-            match x {
-                Ok(value) => match value {
-                    MyValueType::Vec2 { value } => Err(1),
-                    MyValueType::Scalar { value } => Err(2),
-                    MyValueType::Text { value } => Ok(value),
-                    MyValueType::Widget { value } => Err(4)
+        fn input_text(&mut self, name: &str) -> anyhow::Result<String> {
+            let value = self.evaluate_input(name)?;
+            let found = value.data_type();
+            match value {
+                MyValueType::Text { value } => Ok(value),
+                other => match coerce_value(other, MyDataType::Text) {
+                    Some(MyValueType::Text { value }) => Ok(value),
+                    _ => Err(TypeMismatch { expected: MyDataType::Text, found, input: name.to_string() }.into()),
                 },
-                Err(_) => Err(3),
             }
         }
 
@@ -807,8 +1159,12 @@ pub fn evaluate_node(
     }
 
     let node = &graph[node_id];
-    let mut evaluator = Evaluator::new(graph, outputs_cache, node_id);
-    match node.user_data.template {
+    let mut evaluator = Evaluator::new(graph, eval_state, visiting, profiler, node_id);
+    // Wrapped in a closure so an early `?` from any arm still falls through to
+    // the `visiting.remove` below instead of leaving this node stuck marked
+    // as "in progress" for the rest of the call chain.
+    let result = (|| -> anyhow::Result<MyValueType> {
+        match &node.user_data.template {
         MyNodeTemplate::AddScalar => {
             let a = evaluator.input_scalar("A")?;
             let b = evaluator.input_scalar("B")?;
@@ -844,34 +1200,147 @@ pub fn evaluate_node(
             evaluator.output_scalar("out", value)
         }
         MyNodeTemplate::Scaffold => {
-            let header = evaluator.input_widget("header");
-            let body = evaluator.input_widget("body");
-            // print!("{:?}", value);
+            let header = evaluator.input_widget_optional("header")?;
+            let body = evaluator.input_widget("body")?;
             let mut result = json!({});
-            if let Ok(x) = header {
+            if let Some(x) = header {
                 result.as_object_mut().unwrap().insert("header".to_owned(), x);
             }
-            if let Ok(x) = body {
-                result.as_object_mut().unwrap().insert("body".to_owned(), x);
-            }
+            result.as_object_mut().unwrap().insert("body".to_owned(), body);
             evaluator.output_widget("widget", result)
         }
         MyNodeTemplate::Text => {
-            let text = evaluator.input_text("text");
+            let text = evaluator.input_text("text")?;
+            let result = json!({"text": text});
+            evaluator.output_widget("widget", result)
+        },
+        MyNodeTemplate::Dynamic(template) => {
+            // Runtime-generated nodes don't have a fixed formula: the best we
+            // can do generically is surface each declared output as its
+            // default/zero value. Every output port must be populated, since
+            // `evaluate_input` expects the cache to hold a value for any
+            // output it's connected to. A real integration would give
+            // `DynamicTemplate` its own evaluation hook once one is needed.
+            let mut result = MyValueType::Scalar { value: 0.0 };
+            for port in &template.ports {
+                match port {
+                    PortDescriptor::Button { name } => {
+                        result = evaluator.output_scalar(name, 0.0)?;
+                    }
+                    PortDescriptor::WidgetOut { name } => {
+                        result = evaluator.output_widget(name, json!({}))?;
+                    }
+                    PortDescriptor::Slider { .. } | PortDescriptor::ScalarIn { .. } => {}
+                }
+            }
+            Ok(result)
+        }
+        }
+    })();
+    visiting.remove(&node_id);
+    profiler.end_node(node_id);
+    // Only a successful recompute means the cache now reflects this node's
+    // current inputs; leave it dirty on error so the next attempt retries
+    // instead of treating a failed evaluation as settled.
+    if result.is_ok() {
+        eval_state.mark_clean(node_id);
+    }
+    result
+}
 
-            let mut result = json!({"text": ""});
-            if let Ok(text_value) = text {
-                result.as_object_mut().unwrap().insert("text".to_owned(), serde_json::Value::String(text_value));
+/// Computes a topological order over the subgraph of nodes that `root`
+/// transitively depends on, using Kahn's algorithm: in-degrees count, for
+/// each node, how many of its inputs are fed by another node in the same
+/// subgraph; zero-in-degree nodes are repeatedly taken and their successors'
+/// in-degrees decremented. If fewer nodes come out than went in, whatever is
+/// left never reached in-degree zero, i.e. it's part of a cycle.
+fn topological_order(graph: &MyGraph, root: NodeId) -> anyhow::Result<Vec<NodeId>> {
+    let mut subgraph = std::collections::HashSet::new();
+    let mut stack = vec![root];
+    while let Some(node_id) = stack.pop() {
+        if subgraph.insert(node_id) {
+            for (_, input_id) in &graph[node_id].inputs {
+                if let Some(output_id) = graph.connection(*input_id) {
+                    stack.push(graph[output_id].node);
+                }
             }
+        }
+    }
 
-            evaluator.output_widget("widget", result)
-        },
+    let mut in_degree: std::collections::HashMap<NodeId, usize> =
+        subgraph.iter().map(|id| (*id, 0)).collect();
+    let mut successors: std::collections::HashMap<NodeId, Vec<NodeId>> = std::collections::HashMap::new();
+    for node_id in &subgraph {
+        for (_, input_id) in &graph[*node_id].inputs {
+            if let Some(output_id) = graph.connection(*input_id) {
+                let producer = graph[output_id].node;
+                if subgraph.contains(&producer) {
+                    *in_degree.get_mut(node_id).expect("every subgraph node has an entry") += 1;
+                    successors.entry(producer).or_default().push(*node_id);
+                }
+            }
+        }
+    }
+
+    let mut ready: Vec<NodeId> = in_degree
+        .iter()
+        .filter(|(_, degree)| **degree == 0)
+        .map(|(id, _)| *id)
+        .collect();
+    let mut order = Vec::with_capacity(subgraph.len());
+    while let Some(node_id) = ready.pop() {
+        order.push(node_id);
+        if let Some(next) = successors.get(&node_id) {
+            for successor in next {
+                let degree = in_degree.get_mut(successor).expect("successor is in the subgraph");
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(*successor);
+                }
+            }
+        }
     }
+
+    if order.len() != subgraph.len() {
+        anyhow::bail!("cycle detected in the graph: {} of {} dependency nodes never became ready", order.len(), subgraph.len());
+    }
+    Ok(order)
+}
+
+/// Evaluates `root` and everything it transitively depends on in a single
+/// iterative pass over a topological ordering of the subgraph, rather than
+/// recursing through `evaluate_input`. This catches a cycle up front, before
+/// evaluating anything, instead of only as a side effect of unwinding a deep
+/// recursive call chain — and for a long dependency chain it never grows the
+/// call stack in the first place. Crate-internal: `NagaApp::update` and
+/// `bevy_plugin::evaluate_active_node_system` both call this directly, but a
+/// host only ever needs to read its result back out through
+/// [`crate::bevy_plugin::ActiveNodeOutput`].
+pub(crate) fn evaluate_subgraph(
+    graph: &MyGraph,
+    root: NodeId,
+    eval_state: &mut EvalState,
+    profiler: &mut Profiler,
+) -> anyhow::Result<MyValueType> {
+    profiler.reset();
+    let order = topological_order(graph, root)?;
+    let mut root_value = None;
+    for node_id in order {
+        // Dependencies of `node_id` were already evaluated earlier in this
+        // same pass (they precede it in the topological order) and are
+        // clean, so this only ever recomputes `node_id` itself — it never
+        // recurses back into `evaluate_input`.
+        let value = evaluate_node(graph, node_id, eval_state, &mut VisitSet::new(), profiler)?;
+        if node_id == root {
+            root_value = Some(value);
+        }
+    }
+    Ok(root_value.expect("root is always included in its own topological order"))
 }
 
 fn populate_output(
     graph: &MyGraph,
-    outputs_cache: &mut OutputsCache,
+    eval_state: &mut EvalState,
     node_id: NodeId,
     param_name: &str,
     value: MyValueType,
@@ -879,7 +1348,7 @@ fn populate_output(
     let output_id = graph[node_id].get_output(param_name)?;
     let x = value.clone();
 
-    outputs_cache.insert(output_id, value);
+    eval_state.outputs_cache.insert(output_id, value);
     Ok(x)
 }
 
@@ -888,29 +1357,21 @@ fn evaluate_input(
     graph: &MyGraph,
     node_id: NodeId,
     param_name: &str,
-    outputs_cache: &mut OutputsCache,
+    eval_state: &mut EvalState,
+    visiting: &mut VisitSet,
+    profiler: &mut Profiler,
 ) -> anyhow::Result<MyValueType> {
     let input_id = graph[node_id].get_input(param_name)?;
 
     // The output of another node is connected.
     if let Some(other_output_id) = graph.connection(input_id) {
-        // The value was already computed due to the evaluation of some other
-        // node. We simply return value from the cache.
-        if let Some(other_value) = outputs_cache.get(&other_output_id) {
-            let x = other_value.clone();
-            Ok(x)
-        }
-        // This is the first time encountering this node, so we need to
-        // recursively evaluate it.
-        else {
-            // Calling this will populate the cache
-            evaluate_node(graph, graph[other_output_id].node, outputs_cache)?;
-            let x = other_output_id.clone();
-            // let cache = outputs_cache.clone();
-            let xx = outputs_cache.get(&other_output_id).map(|x| (*x).clone());
-            // Now that we know the value is cached, return it
-            Ok(xx.expect("Cache should be populated"))
-        }
+        // `evaluate_node` already returns a cached value without recomputing
+        // when the producing node is clean, so it's always safe to call
+        // here rather than duplicating that check.
+        evaluate_node(graph, graph[other_output_id].node, eval_state, visiting, profiler)?;
+        let xx = eval_state.outputs_cache.get(&other_output_id).cloned();
+        // Now that we know the value is cached, return it
+        Ok(xx.expect("Cache should be populated"))
     }
     // No existing connection, take the inline value instead.
     else {
@@ -918,3 +1379,98 @@ fn evaluate_input(
         Ok(x)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(graph: &mut MyGraph, user_state: &mut MyGraphState, template: MyNodeTemplate) -> NodeId {
+        graph.add_node(
+            template.node_graph_label(user_state),
+            template.user_data(user_state),
+            |graph, node_id| template.build_node(graph, user_state, node_id),
+        )
+    }
+
+    fn make_scalar(graph: &mut MyGraph, user_state: &mut MyGraphState, value: f32) -> NodeId {
+        let node_id = spawn(graph, user_state, MyNodeTemplate::MakeScalar);
+        let input_id = graph[node_id].get_input("value").unwrap();
+        graph.inputs[input_id].value = MyValueType::Scalar { value };
+        node_id
+    }
+
+    fn assert_scalar(value: MyValueType, expected: f32) {
+        match value {
+            MyValueType::Scalar { value } => assert_eq!(value, expected),
+            other => panic!("expected a Scalar, got {:?}", other),
+        }
+    }
+
+    fn connect_by_name(graph: &mut MyGraph, from: NodeId, output: &str, to: NodeId, input: &str) {
+        let output_id = graph[from].get_output(output).unwrap();
+        let input_id = graph[to].get_input(input).unwrap();
+        graph.add_connection(output_id, input_id);
+    }
+
+    #[test]
+    fn topological_order_puts_dependencies_before_dependents() {
+        let mut graph = MyGraph::default();
+        let mut user_state = MyGraphState::default();
+        let a = make_scalar(&mut graph, &mut user_state, 2.0);
+        let b = make_scalar(&mut graph, &mut user_state, 3.0);
+        let sum = spawn(&mut graph, &mut user_state, MyNodeTemplate::AddScalar);
+        connect_by_name(&mut graph, a, "out", sum, "A");
+        connect_by_name(&mut graph, b, "out", sum, "B");
+
+        let order = topological_order(&graph, sum).unwrap();
+
+        assert_eq!(order.len(), 3);
+        let sum_index = order.iter().position(|id| *id == sum).unwrap();
+        assert!(order[..sum_index].contains(&a));
+        assert!(order[..sum_index].contains(&b));
+    }
+
+    #[test]
+    fn topological_order_detects_a_cycle() {
+        let mut graph = MyGraph::default();
+        let mut user_state = MyGraphState::default();
+        let a = spawn(&mut graph, &mut user_state, MyNodeTemplate::AddScalar);
+        let b = spawn(&mut graph, &mut user_state, MyNodeTemplate::AddScalar);
+        // a -> b -> a: neither node's own in-degree ever reaches zero.
+        connect_by_name(&mut graph, a, "out", b, "A");
+        connect_by_name(&mut graph, b, "out", a, "A");
+
+        assert!(topological_order(&graph, a).is_err());
+    }
+
+    #[test]
+    fn evaluate_subgraph_computes_through_a_chain() {
+        let mut graph = MyGraph::default();
+        let mut user_state = MyGraphState::default();
+        let mut eval_state = EvalState::default();
+        let mut profiler = Profiler::default();
+        let a = make_scalar(&mut graph, &mut user_state, 2.0);
+        let b = make_scalar(&mut graph, &mut user_state, 3.0);
+        let sum = spawn(&mut graph, &mut user_state, MyNodeTemplate::AddScalar);
+        connect_by_name(&mut graph, a, "out", sum, "A");
+        connect_by_name(&mut graph, b, "out", sum, "B");
+
+        let result = evaluate_subgraph(&graph, sum, &mut eval_state, &mut profiler).unwrap();
+
+        assert_scalar(result, 5.0);
+    }
+
+    #[test]
+    fn evaluate_subgraph_errors_on_a_cycle() {
+        let mut graph = MyGraph::default();
+        let mut user_state = MyGraphState::default();
+        let mut eval_state = EvalState::default();
+        let mut profiler = Profiler::default();
+        let a = spawn(&mut graph, &mut user_state, MyNodeTemplate::AddScalar);
+        let b = spawn(&mut graph, &mut user_state, MyNodeTemplate::AddScalar);
+        connect_by_name(&mut graph, a, "out", b, "A");
+        connect_by_name(&mut graph, b, "out", a, "A");
+
+        assert!(evaluate_subgraph(&graph, a, &mut eval_state, &mut profiler).is_err());
+    }
+}
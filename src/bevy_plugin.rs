@@ -0,0 +1,169 @@
+//! Packages the node-graph editor and evaluator as a Bevy plugin, so a Bevy
+//! app can embed the same graph this crate otherwise drives through a
+//! standalone `eframe` loop (see `app.rs`). Gated behind the `bevy` feature
+//! since it pulls in `bevy`/`bevy_egui` on top of this crate's normal
+//! dependencies, and a Bevy app doesn't need the `eframe::App` entry point
+//! `main.rs` uses.
+//!
+//! [`NodeGraphPlugin`] inserts the graph (wrapped in [`GraphResource`]) and
+//! its user state ([`GraphStateResource`]) as resources, schedules
+//! [`graph_editor_system`] to draw the editor inside a `bevy_egui` window
+//! each frame and apply whatever edits the user made, and schedules
+//! [`evaluate_active_node_system`] to re-evaluate the active node afterwards
+//! and publish its result to [`ActiveNodeOutput`]. Other systems read that
+//! resource to feed live graph output into the rest of the world, the same
+//! way the desktop app's active-node overlay reads `evaluate_subgraph`
+//! without reimplementing it.
+
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::graph_ui::egui_compat::egui;
+
+use crate::app::{
+    discover_dynamic_templates, evaluate_subgraph, AllMyNodeTemplates, DynamicTemplate,
+    MyEditorState, MyGraphState, MyValueType,
+};
+use crate::commands::{self, CommandHistory, ParamSnapshot};
+use crate::eval_state::EvalState;
+use crate::graph_ui::id_type::NodeId;
+use crate::profiler::Profiler;
+
+/// The graph's topology and editor layout (node positions, pan/zoom,
+/// selection). Wraps the same [`MyEditorState`] `NagaApp` keeps in its
+/// `state` field.
+#[derive(Resource, Default)]
+pub struct GraphResource(pub MyEditorState);
+
+/// Per-graph user state — currently just which node is "active", i.e. has
+/// its result published to [`ActiveNodeOutput`]. Wraps the same
+/// [`MyGraphState`] `NagaApp` keeps in its `user_state` field.
+#[derive(Resource, Default)]
+pub struct GraphStateResource(pub MyGraphState);
+
+/// Bookkeeping the editor needs across frames (undo/redo history, the
+/// constant-value snapshot undo diffing compares against, discovered
+/// dynamic templates, the incremental evaluation cache, and the opt-in
+/// profiler). Not meant to be read by other systems; it exists only so
+/// [`graph_editor_system`]/[`evaluate_active_node_system`] have somewhere to
+/// keep it between frames, mirroring the rest of `NagaApp`'s fields.
+#[derive(Resource)]
+pub struct GraphEditorRuntime {
+    command_history: CommandHistory,
+    param_snapshot: ParamSnapshot,
+    dynamic_templates: Vec<DynamicTemplate>,
+    eval_state: EvalState,
+    profiler: Profiler,
+}
+
+impl Default for GraphEditorRuntime {
+    fn default() -> Self {
+        Self {
+            command_history: CommandHistory::default(),
+            param_snapshot: ParamSnapshot::default(),
+            dynamic_templates: discover_dynamic_templates(),
+            eval_state: EvalState::default(),
+            profiler: Profiler::default(),
+        }
+    }
+}
+
+/// The evaluated output of the currently active node, refreshed once per
+/// frame by [`evaluate_active_node_system`]. `version` mirrors
+/// `EvalState::version`, so a consumer can tell a real recomputation apart
+/// from a frame that just replayed a cached result — the same distinction
+/// the desktop app's overlay text shows alongside its result.
+#[derive(Resource, Default)]
+pub struct ActiveNodeOutput {
+    pub node: Option<NodeId>,
+    pub version: u64,
+    pub result: Option<anyhow::Result<MyValueType>>,
+}
+
+/// Draws the node graph editor inside an egui window each frame and applies
+/// whatever edits the user made (new connections, moved nodes, param edits,
+/// deletions) via the same [`commands`] functions `NagaApp::update` uses.
+pub fn graph_editor_system(
+    mut contexts: EguiContexts,
+    mut graph: ResMut<GraphResource>,
+    mut user_state: ResMut<GraphStateResource>,
+    mut runtime: ResMut<GraphEditorRuntime>,
+) {
+    let ctx = contexts.ctx_mut();
+    let graph = &mut graph.0;
+    let runtime = &mut *runtime;
+
+    egui::Window::new("Node Graph").show(ctx, |ui| {
+        let response = graph.draw_graph_editor(
+            ui,
+            AllMyNodeTemplates {
+                dynamic: runtime.dynamic_templates.clone(),
+            },
+            &mut user_state.0,
+            Vec::default(),
+        );
+
+        for node_response in response.node_responses {
+            commands::apply_node_response(
+                &mut runtime.command_history,
+                graph,
+                &mut runtime.eval_state,
+                &mut user_state.0,
+                node_response,
+            );
+        }
+    });
+
+    runtime.param_snapshot = commands::record_param_edits(
+        &mut runtime.command_history,
+        &graph.graph,
+        &mut runtime.eval_state,
+        &runtime.param_snapshot,
+    );
+}
+
+/// Re-evaluates the active node (if any) and publishes its result to
+/// [`ActiveNodeOutput`], so other Bevy systems can read live graph output
+/// without reimplementing `evaluate_subgraph` themselves.
+pub fn evaluate_active_node_system(
+    graph: Res<GraphResource>,
+    user_state: Res<GraphStateResource>,
+    mut runtime: ResMut<GraphEditorRuntime>,
+    mut output: ResMut<ActiveNodeOutput>,
+) {
+    let Some(node) = user_state.0.active_node else {
+        output.node = None;
+        return;
+    };
+    if !graph.0.graph.nodes.contains_key(node) {
+        output.node = None;
+        return;
+    }
+
+    let result = evaluate_subgraph(
+        &graph.0.graph,
+        node,
+        &mut runtime.eval_state,
+        &mut runtime.profiler,
+    );
+    let version = runtime.eval_state.version(node);
+    if output.node != Some(node) || output.version != version {
+        output.node = Some(node);
+        output.version = version;
+        output.result = Some(result);
+    }
+}
+
+/// Adds the node graph editor and evaluator to a Bevy app. Insert this
+/// alongside `bevy_egui::EguiPlugin`.
+pub struct NodeGraphPlugin;
+
+impl Plugin for NodeGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GraphResource>()
+            .init_resource::<GraphStateResource>()
+            .init_resource::<GraphEditorRuntime>()
+            .init_resource::<ActiveNodeOutput>()
+            .add_systems(Update, (graph_editor_system, evaluate_active_node_system).chain());
+    }
+}
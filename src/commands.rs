@@ -0,0 +1,559 @@
+//! Undo/redo for the node editor, modeled as an explicit command stack.
+//!
+//! Every structural edit the user makes (add/remove a node, connect or
+//! disconnect two ports, drag a node, edit a param) is represented as a
+//! `Command` that knows how to apply itself and how to build its own
+//! inverse. `CommandHistory` keeps a done/undone stack pair; pushing a new
+//! command always clears the redo stack, matching the usual editor
+//! convention that redo history is invalidated by a fresh edit.
+
+use slotmap::SecondaryMap;
+
+use crate::graph_ui::egui_compat::egui::Pos2;
+
+use crate::app::{MyEditorState, MyGraph, MyGraphState, MyNodeData, MyNodeTemplate, MyResponse, MyValueType};
+use crate::eval_state::EvalState;
+use crate::graph_ui::editor_ui::NodeResponse;
+use crate::graph_ui::errors::EguiGraphError;
+use crate::graph_ui::id_type::{InputId, NodeId, OutputId};
+use crate::graph_ui::traits::NodeTemplateTrait;
+
+/// Everything needed to recreate a node that was removed from the graph,
+/// since the node's own `InputId`/`OutputId`s cannot be reinserted into the
+/// slotmaps under their original keys. Connections to ports on *other*
+/// (still-alive) nodes are restored by id; the removed node's own ports are
+/// restored by re-running `build_node` and then looking their fresh ids up
+/// by name.
+#[derive(Clone)]
+pub struct RemovedNodeSnapshot {
+    pub template: MyNodeTemplate,
+    pub pos: Pos2,
+    pub order_index: usize,
+    pub input_values: Vec<(String, MyValueType)>,
+    /// local input name -> the output (on another node) that fed it
+    pub incoming: Vec<(String, OutputId)>,
+    /// local output name -> an input (on another node) that it fed
+    pub outgoing: Vec<(String, InputId)>,
+}
+
+#[derive(Clone)]
+pub enum Command {
+    AddNode {
+        template: MyNodeTemplate,
+        pos: Pos2,
+        node_id: NodeId,
+    },
+    RemoveNode {
+        id: NodeId,
+        snapshot: RemovedNodeSnapshot,
+    },
+    Connect {
+        output: OutputId,
+        input: InputId,
+    },
+    Disconnect {
+        output: OutputId,
+        input: InputId,
+    },
+    MoveNode {
+        id: NodeId,
+        from: Pos2,
+        to: Pos2,
+    },
+    SetParam {
+        node: NodeId,
+        param: InputId,
+        old: MyValueType,
+        new: MyValueType,
+    },
+}
+
+impl Command {
+    /// Captures everything needed to undo removing `node_id`, then actually
+    /// removes it from the graph. Call this instead of `graph.remove_node`
+    /// directly whenever the removal should be undoable.
+    pub fn capture_remove_node(
+        graph: &mut MyGraph,
+        node_order: &mut Vec<NodeId>,
+        node_positions: &mut SecondaryMap<NodeId, Pos2>,
+        eval_state: &mut EvalState,
+        node_id: NodeId,
+    ) -> Command {
+        let template = graph.nodes[node_id].user_data.template.clone();
+        let pos = node_positions.get(node_id).copied().unwrap_or_default();
+        let order_index = node_order.iter().position(|id| *id == node_id).unwrap_or(0);
+
+        let input_values = graph.nodes[node_id]
+            .inputs
+            .iter()
+            .map(|(name, input_id)| (name.clone(), graph.inputs[*input_id].value.clone()))
+            .collect::<Vec<_>>();
+
+        let (removed, severed) = graph.remove_node(node_id);
+
+        let mut incoming = Vec::new();
+        let mut outgoing = Vec::new();
+        let mut affected_consumers = Vec::new();
+        for (input, output) in severed {
+            if let Some((name, _)) = removed.inputs.iter().find(|(_, id)| *id == input) {
+                incoming.push((name.clone(), output));
+            } else if let Some((name, _)) = removed.outputs.iter().find(|(_, id)| *id == output) {
+                // The node on the other end of this connection survives the
+                // removal, but its cached result was computed from an input
+                // that's now gone.
+                affected_consumers.push(graph.inputs[input].node);
+                outgoing.push((name.clone(), input));
+            }
+        }
+        eval_state.mark_many_dirty(graph, affected_consumers);
+        // The removed node's own cached results no longer correspond to
+        // anything: drop them instead of leaking them forever.
+        eval_state.forget_node(node_id, removed.outputs.iter().map(|(_, id)| *id));
+
+        node_order.retain(|id| *id != node_id);
+        node_positions.remove(node_id);
+
+        Command::RemoveNode {
+            id: node_id,
+            snapshot: RemovedNodeSnapshot {
+                template,
+                pos,
+                order_index,
+                input_values,
+                incoming,
+                outgoing,
+            },
+        }
+    }
+}
+
+/// Two stacks of commands: `done` can be undone, `undone` can be redone.
+/// Pushing a new command (via [`CommandHistory::push`]) clears `undone`.
+#[derive(Default)]
+pub struct CommandHistory {
+    done: Vec<Command>,
+    undone: Vec<Command>,
+}
+
+impl CommandHistory {
+    pub fn push(&mut self, command: Command) {
+        self.undone.clear();
+        self.done.push(command);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Pops the last applied command, applies its inverse against the graph,
+    /// and moves it onto the redo stack.
+    pub fn undo(
+        &mut self,
+        graph: &mut MyGraph,
+        node_order: &mut Vec<NodeId>,
+        node_positions: &mut SecondaryMap<NodeId, Pos2>,
+        user_state: &mut MyGraphState,
+        eval_state: &mut EvalState,
+    ) {
+        let Some(mut command) = self.done.pop() else {
+            return;
+        };
+        apply_inverse(&mut command, graph, node_order, node_positions, user_state, eval_state);
+        self.undone.push(command);
+    }
+
+    /// Pops the last undone command and re-applies it, moving it back onto
+    /// the undo stack.
+    pub fn redo(
+        &mut self,
+        graph: &mut MyGraph,
+        node_order: &mut Vec<NodeId>,
+        node_positions: &mut SecondaryMap<NodeId, Pos2>,
+        user_state: &mut MyGraphState,
+        eval_state: &mut EvalState,
+    ) {
+        let Some(mut command) = self.undone.pop() else {
+            return;
+        };
+        apply_forward(&mut command, graph, node_order, node_positions, user_state, eval_state);
+        self.done.push(command);
+    }
+
+    /// If the last applied command was a move of `id`, updates its
+    /// destination in place instead of pushing a new command. Used so that
+    /// dragging a node across many frames undoes as a single step rather
+    /// than one step per frame.
+    fn merge_move_node(&mut self, id: NodeId, to: Pos2) -> bool {
+        match self.done.last_mut() {
+            Some(Command::MoveNode { id: last_id, to: last_to, .. }) if *last_id == id => {
+                *last_to = to;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Applies `command` to the graph, re-deriving node ids that only became
+/// valid just now (a respawned `AddNode`'s fresh id, a re-removed
+/// `RemoveNode`'s fresh snapshot) so the command can be inverted again later.
+fn apply_forward(
+    command: &mut Command,
+    graph: &mut MyGraph,
+    node_order: &mut Vec<NodeId>,
+    node_positions: &mut SecondaryMap<NodeId, Pos2>,
+    user_state: &mut MyGraphState,
+    eval_state: &mut EvalState,
+) {
+    match command {
+        Command::AddNode { template, pos, node_id } => {
+            *node_id = spawn_node(graph, node_order, node_positions, user_state, template.clone(), *pos);
+        }
+        Command::RemoveNode { id, snapshot } => {
+            if graph.nodes.contains_key(*id) {
+                let command = Command::capture_remove_node(graph, node_order, node_positions, eval_state, *id);
+                if let Command::RemoveNode { snapshot: new_snapshot, .. } = command {
+                    *snapshot = new_snapshot;
+                }
+            }
+        }
+        Command::Connect { output, input } => {
+            graph.add_connection(*output, *input);
+            eval_state.mark_dirty(graph, graph.inputs[*input].node);
+        }
+        Command::Disconnect { input, .. } => {
+            graph.remove_connection(*input);
+            eval_state.mark_dirty(graph, graph.inputs[*input].node);
+        }
+        Command::MoveNode { id, to, .. } => {
+            node_positions.insert(*id, *to);
+        }
+        Command::SetParam { param, new, .. } => {
+            graph.inputs[*param].value = new.clone();
+            eval_state.mark_dirty(graph, graph.inputs[*param].node);
+        }
+    }
+}
+
+/// Applies the inverse of `command` to the graph. Like [`apply_forward`],
+/// updates ids/snapshots that change as a result (restoring a removed node
+/// hands out a fresh id, which `RemoveNode::id` must track for the next
+/// redo).
+fn apply_inverse(
+    command: &mut Command,
+    graph: &mut MyGraph,
+    node_order: &mut Vec<NodeId>,
+    node_positions: &mut SecondaryMap<NodeId, Pos2>,
+    user_state: &mut MyGraphState,
+    eval_state: &mut EvalState,
+) {
+    match command {
+        Command::AddNode { node_id, .. } => {
+            if graph.nodes.contains_key(*node_id) {
+                Command::capture_remove_node(graph, node_order, node_positions, eval_state, *node_id);
+            }
+        }
+        Command::RemoveNode { id, snapshot } => {
+            *id = restore_node(graph, node_order, node_positions, user_state, eval_state, snapshot);
+        }
+        Command::Connect { input, .. } => {
+            graph.remove_connection(*input);
+            eval_state.mark_dirty(graph, graph.inputs[*input].node);
+        }
+        Command::Disconnect { output, input } => {
+            graph.add_connection(*output, *input);
+            eval_state.mark_dirty(graph, graph.inputs[*input].node);
+        }
+        Command::MoveNode { id, from, .. } => {
+            node_positions.insert(*id, *from);
+        }
+        Command::SetParam { param, old, .. } => {
+            graph.inputs[*param].value = old.clone();
+            eval_state.mark_dirty(graph, graph.inputs[*param].node);
+        }
+    }
+}
+
+fn spawn_node(
+    graph: &mut MyGraph,
+    node_order: &mut Vec<NodeId>,
+    node_positions: &mut SecondaryMap<NodeId, Pos2>,
+    user_state: &mut MyGraphState,
+    template: MyNodeTemplate,
+    pos: Pos2,
+) -> NodeId {
+    let node_id = graph.add_node(
+        template.node_graph_label(user_state),
+        template.user_data(user_state),
+        |graph, node_id| template.build_node(graph, user_state, node_id),
+    );
+    node_positions.insert(node_id, pos);
+    node_order.push(node_id);
+    node_id
+}
+
+/// Adds a node through the template the same way the node finder would, and
+/// records the edit so it can be undone.
+pub fn do_add_node(
+    history: &mut CommandHistory,
+    graph: &mut MyGraph,
+    node_order: &mut Vec<NodeId>,
+    node_positions: &mut SecondaryMap<NodeId, Pos2>,
+    user_state: &mut MyGraphState,
+    template: MyNodeTemplate,
+    pos: Pos2,
+) -> NodeId {
+    let node_id = spawn_node(graph, node_order, node_positions, user_state, template.clone(), pos);
+    history.push(Command::AddNode { template, pos, node_id });
+    node_id
+}
+
+/// Removes a node and records the edit so it can be undone.
+pub fn do_remove_node(
+    history: &mut CommandHistory,
+    graph: &mut MyGraph,
+    node_order: &mut Vec<NodeId>,
+    node_positions: &mut SecondaryMap<NodeId, Pos2>,
+    eval_state: &mut EvalState,
+    node_id: NodeId,
+) {
+    let command = Command::capture_remove_node(graph, node_order, node_positions, eval_state, node_id);
+    history.push(command);
+}
+
+/// Connects `output` to `input` and records the edit so it can be undone.
+/// Disconnects whatever `input` was previously wired to first, matching how
+/// the node graph only allows one connection per input. Rejects the
+/// connection (leaving the graph untouched) if it would create a cycle or
+/// join incompatible data types; see `Graph::validate_connection`.
+pub fn do_connect(
+    history: &mut CommandHistory,
+    graph: &mut MyGraph,
+    eval_state: &mut EvalState,
+    output: OutputId,
+    input: InputId,
+) -> Result<(), EguiGraphError> {
+    if graph.connection(input) == Some(output) {
+        return Ok(());
+    }
+    graph.validate_connection(output, input)?;
+
+    if let Some(previous_output) = graph.connection(input) {
+        graph.remove_connection(input);
+        history.push(Command::Disconnect { output: previous_output, input });
+    }
+    graph.add_connection(output, input);
+    history.push(Command::Connect { output, input });
+    // The input's owner now reads from a (possibly different) upstream
+    // value, so its cached result — and anything downstream of it — is stale.
+    eval_state.mark_dirty(graph, graph.inputs[input].node);
+    Ok(())
+}
+
+/// Disconnects `input` (if connected) and records the edit so it can be
+/// undone.
+pub fn do_disconnect(
+    history: &mut CommandHistory,
+    graph: &mut MyGraph,
+    eval_state: &mut EvalState,
+    input: InputId,
+) {
+    if let Some(output) = graph.remove_connection(input) {
+        history.push(Command::Disconnect { output, input });
+        // `evaluate_input` now falls back to the inline constant for this
+        // input instead of the upstream value, so the owning node's cached
+        // result is stale too.
+        eval_state.mark_dirty(graph, graph.inputs[input].node);
+    }
+}
+
+/// Moves a node to `to` and records the edit so it can be undone. `from`
+/// should be the node's position immediately before this move.
+///
+/// A drag reports a new position every frame; if the last command already
+/// moved this same node, its destination is updated in place rather than
+/// pushing another command, so the whole drag undoes in one step.
+pub fn do_move_node(
+    history: &mut CommandHistory,
+    node_positions: &mut SecondaryMap<NodeId, Pos2>,
+    id: NodeId,
+    from: Pos2,
+    to: Pos2,
+) {
+    node_positions.insert(id, to);
+    if history.merge_move_node(id, to) {
+        return;
+    }
+    history.push(Command::MoveNode { id, from, to });
+}
+
+/// Applies a single `NodeResponse` from the graph editor widget the same way
+/// regardless of which front end is driving it (the desktop `eframe` loop or
+/// the Bevy plugin): records undoable commands for structural edits, and
+/// updates `user_state.active_node` for the app-specific `MyResponse`
+/// events. Pulled out so the two front ends can't silently diverge on how a
+/// response is handled.
+pub fn apply_node_response(
+    history: &mut CommandHistory,
+    state: &mut MyEditorState,
+    eval_state: &mut EvalState,
+    user_state: &mut MyGraphState,
+    response: NodeResponse<MyResponse, MyNodeData>,
+) {
+    match response {
+        NodeResponse::User(user_event) => match user_event {
+            MyResponse::SetActiveNode(node) => user_state.active_node = Some(node),
+            MyResponse::ClearActiveNode => user_state.active_node = None,
+        },
+        NodeResponse::ConnectEventEnded { output, input } => {
+            if let Err(err) = do_connect(history, &mut state.graph, eval_state, output, input) {
+                eprintln!("rejected connection: {}", err);
+            }
+        }
+        NodeResponse::DisconnectEvent { input } => {
+            do_disconnect(history, &mut state.graph, eval_state, input);
+        }
+        NodeResponse::MoveNode { node, drag_delta } => {
+            let from = state.node_positions.get(node).copied().unwrap_or_default();
+            do_move_node(history, &mut state.node_positions, node, from, from + drag_delta);
+        }
+        NodeResponse::SelectNode(node) => {
+            state.selected_nodes = vec![node];
+        }
+        NodeResponse::DeleteNodeUi(node) => {
+            do_remove_node(
+                history,
+                &mut state.graph,
+                &mut state.node_order,
+                &mut state.node_positions,
+                eval_state,
+                node,
+            );
+        }
+        // The node finder builds the node itself (it only has access to the
+        // generic `NodeTemplateTrait`, not `CommandHistory`), so the command
+        // recording it for undo has to happen retroactively here, the same
+        // way `Command::capture_remove_node` reads a node's template back out
+        // of its own `user_data` rather than being handed it directly.
+        NodeResponse::CreatedNode(node_id) => {
+            if state.graph.nodes.contains_key(node_id) {
+                let template = state.graph.nodes[node_id].user_data.template.clone();
+                let pos = state.node_positions.get(node_id).copied().unwrap_or_default();
+                history.push(Command::AddNode { template, pos, node_id });
+            }
+        }
+        // RaiseNode/DeleteNodeFull/ConnectEventStarted carry no extra state
+        // either front end needs to react to beyond what's already handled
+        // above (the in-progress wire is tracked by the graph editor itself
+        // until it lands on a port).
+        NodeResponse::RaiseNode(_)
+        | NodeResponse::DeleteNodeFull { .. }
+        | NodeResponse::ConnectEventStarted(..) => {}
+    }
+}
+
+/// Snapshot of every input's inline value, taken once per frame so edits
+/// made through a param's widget (which mutate `graph.inputs` directly,
+/// since they're drawn deep inside the immutable-pass graph editor) can
+/// still be recorded as undoable `SetParam` commands after the fact.
+pub type ParamSnapshot = SecondaryMap<InputId, MyValueType>;
+
+pub fn snapshot_params(graph: &MyGraph) -> ParamSnapshot {
+    graph
+        .inputs
+        .iter()
+        .map(|(id, param)| (id, param.value.clone()))
+        .collect()
+}
+
+/// Compares `graph`'s current input values against `previous`, pushes a
+/// `SetParam` command for each one that changed, and returns an updated
+/// snapshot to use as `previous` next frame.
+pub fn record_param_edits(
+    history: &mut CommandHistory,
+    graph: &MyGraph,
+    eval_state: &mut EvalState,
+    previous: &ParamSnapshot,
+) -> ParamSnapshot {
+    let mut changed_nodes = Vec::new();
+    for (id, param) in graph.inputs.iter() {
+        if let Some(old) = previous.get(id) {
+            if !values_equal(old, &param.value) {
+                history.push(Command::SetParam {
+                    node: param.node,
+                    param: id,
+                    old: old.clone(),
+                    new: param.value.clone(),
+                });
+                changed_nodes.push(param.node);
+            }
+        }
+    }
+    eval_state.mark_many_dirty(graph, changed_nodes);
+    snapshot_params(graph)
+}
+
+fn values_equal(a: &MyValueType, b: &MyValueType) -> bool {
+    match (a, b) {
+        (MyValueType::Vec2 { value: a }, MyValueType::Vec2 { value: b }) => a == b,
+        (MyValueType::Scalar { value: a }, MyValueType::Scalar { value: b }) => a == b,
+        (MyValueType::Widget { value: a }, MyValueType::Widget { value: b }) => a == b,
+        (MyValueType::Text { value: a }, MyValueType::Text { value: b }) => a == b,
+        _ => false,
+    }
+}
+
+fn restore_node(
+    graph: &mut MyGraph,
+    node_order: &mut Vec<NodeId>,
+    node_positions: &mut SecondaryMap<NodeId, Pos2>,
+    user_state: &mut MyGraphState,
+    eval_state: &mut EvalState,
+    snapshot: &RemovedNodeSnapshot,
+) -> NodeId {
+    let node_id = spawn_node(
+        graph,
+        node_order,
+        node_positions,
+        user_state,
+        snapshot.template.clone(),
+        snapshot.pos,
+    );
+    if let Some(last) = node_order.pop() {
+        debug_assert_eq!(last, node_id);
+        let insert_at = snapshot.order_index.min(node_order.len());
+        node_order.insert(insert_at, node_id);
+    }
+
+    for (name, value) in &snapshot.input_values {
+        if let Ok(input_id) = graph.nodes[node_id].get_input(name) {
+            graph.inputs[input_id].value = value.clone();
+        }
+    }
+    for (name, output) in &snapshot.incoming {
+        if let Ok(input_id) = graph.nodes[node_id].get_input(name) {
+            graph.add_connection(*output, input_id);
+        }
+    }
+    let mut affected_consumers = Vec::new();
+    for (name, input) in &snapshot.outgoing {
+        if let Ok(output_id) = graph.nodes[node_id].get_output(name) {
+            // Only an input that survived the original removal can still be
+            // connected to; it always has, since only `node_id` was removed.
+            if graph.inputs.contains_key(*input) {
+                graph.add_connection(output_id, *input);
+                // That input's owner now reads from a freshly restored
+                // output instead of its inline fallback, so its cached
+                // result is stale.
+                affected_consumers.push(graph.inputs[*input].node);
+            }
+        }
+    }
+    eval_state.mark_many_dirty(graph, affected_consumers);
+
+    node_id
+}
@@ -0,0 +1,406 @@
+//! Graphviz DOT import/export for `MyGraph`.
+//!
+//! `export` renders a graph as a small, self-contained DOT document: one node
+//! statement per `NodeId`, carrying its template and inline parameter values
+//! as attributes, and one edge statement per connection in `nodeA:out ->
+//! nodeB:input` port syntax. `import` reverses this, instantiating node
+//! templates the same way the node finder would and re-adding connections by
+//! matching `get_input`/`get_output` parameter names.
+//!
+//! This isn't a general Graphviz DOT parser — it only needs to read back what
+//! `export` writes, so it parses that exact shape rather than the full DOT
+//! grammar (subgraphs, `graph`/`node`/`edge` defaults, unquoted HTML labels,
+//! and so on aren't supported).
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::app::{DynamicTemplate, MyGraph, MyGraphState, MyNodeTemplate, MyValueType};
+use crate::graph_ui::egui_compat::egui;
+use crate::graph_ui::id_type::NodeId;
+use crate::graph_ui::traits::NodeTemplateTrait;
+
+/// Renders `graph` as a DOT document. Node ids in the document (`n0`, `n1`,
+/// ...) are assigned fresh on each call and don't correspond to anything
+/// stable; everything needed to reconstruct the graph lives in the node and
+/// edge attributes.
+pub fn export(graph: &MyGraph) -> String {
+    let ids: HashMap<NodeId, String> = graph
+        .iter_nodes()
+        .enumerate()
+        .map(|(index, node_id)| (node_id, format!("n{index}")))
+        .collect();
+
+    let mut dot = String::from("digraph naga_graph {\n");
+
+    for node_id in graph.iter_nodes() {
+        let node = &graph[node_id];
+        let values = node
+            .inputs
+            .iter()
+            .map(|(name, input_id)| {
+                escape_field(&format!("{}={}", name, encode_value(&graph.inputs[*input_id].value)))
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        dot.push_str(&format!(
+            "  {} [label={}, template={}, values={}];\n",
+            ids[&node_id],
+            quote(&node.label),
+            quote(&template_tag(&node.user_data.template)),
+            quote(&values),
+        ));
+    }
+
+    for (input_id, output_id) in graph.iter_connections() {
+        let output = graph.get_output(output_id);
+        let input = graph.get_input(input_id);
+        let out_name = &graph[output.node]
+            .outputs
+            .iter()
+            .find(|(_, id)| *id == output_id)
+            .expect("output belongs to its own node")
+            .0;
+        let in_name = &graph[input.node]
+            .inputs
+            .iter()
+            .find(|(_, id)| *id == input_id)
+            .expect("input belongs to its own node")
+            .0;
+        dot.push_str(&format!(
+            "  {}:{} -> {}:{};\n",
+            ids[&output.node], out_name, ids[&input.node], in_name,
+        ));
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// Parses a document produced by [`export`] back into a graph. `Dynamic`
+/// nodes are matched against `known_dynamic` by label, since their ports
+/// aren't fixed at compile time and so can't be reconstructed from the tag
+/// alone; a tag with no matching label is an error rather than a guess.
+pub fn import(dot: &str, known_dynamic: &[Rc<DynamicTemplate>]) -> anyhow::Result<MyGraph> {
+    let mut graph = MyGraph::default();
+    let mut user_state = MyGraphState::default();
+    let mut ids: HashMap<String, NodeId> = HashMap::new();
+    let mut edges = Vec::new();
+
+    for raw_line in dot.lines() {
+        let line = raw_line.trim().trim_end_matches(';').trim();
+        if line.is_empty() || line.starts_with("digraph") || line == "}" {
+            continue;
+        }
+
+        if let Some(arrow) = line.find("->") {
+            let (out_port, in_port) = (line[..arrow].trim(), line[arrow + 2..].trim());
+            edges.push((split_port(out_port)?, split_port(in_port)?));
+            continue;
+        }
+
+        let bracket = line
+            .find('[')
+            .ok_or_else(|| anyhow::anyhow!("unrecognized DOT statement: '{line}'"))?;
+        let close = line
+            .rfind(']')
+            .ok_or_else(|| anyhow::anyhow!("node statement missing closing ']': '{line}'"))?;
+        let dot_id = line[..bracket].trim().to_string();
+        let attrs = parse_attrs(&line[bracket + 1..close]);
+
+        let template_tag_value = attrs
+            .get("template")
+            .ok_or_else(|| anyhow::anyhow!("node '{dot_id}' is missing a 'template' attribute"))?;
+        let template = parse_template_tag(template_tag_value, known_dynamic)?;
+
+        let node_id = graph.add_node(
+            template.node_graph_label(&mut user_state),
+            template.user_data(&mut user_state),
+            |graph, node_id| template.build_node(graph, &mut user_state, node_id),
+        );
+
+        if let Some(values) = attrs.get("values") {
+            for field in split_fields(values).into_iter().filter(|f| !f.is_empty()) {
+                let (name, encoded) = field
+                    .split_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("malformed value field on node '{dot_id}': '{field}'"))?;
+                let input_id = graph[node_id].get_input(name)?;
+                graph.inputs[input_id].value = decode_value(encoded)?;
+            }
+        }
+
+        ids.insert(dot_id, node_id);
+    }
+
+    for ((out_id, out_port), (in_id, in_port)) in edges {
+        let producer = *ids
+            .get(&out_id)
+            .ok_or_else(|| anyhow::anyhow!("edge refers to unknown node '{out_id}'"))?;
+        let consumer = *ids
+            .get(&in_id)
+            .ok_or_else(|| anyhow::anyhow!("edge refers to unknown node '{in_id}'"))?;
+        let output_id = graph[producer].get_output(&out_port)?;
+        let input_id = graph[consumer].get_input(&in_port)?;
+        graph.add_connection(output_id, input_id);
+    }
+
+    Ok(graph)
+}
+
+fn split_port(s: &str) -> anyhow::Result<(String, String)> {
+    s.split_once(':')
+        .map(|(id, port)| (id.to_string(), port.to_string()))
+        .ok_or_else(|| anyhow::anyhow!("expected 'node:port', got '{s}'"))
+}
+
+fn template_tag(template: &MyNodeTemplate) -> String {
+    match template {
+        MyNodeTemplate::MakeScalar => "MakeScalar".to_string(),
+        MyNodeTemplate::AddScalar => "AddScalar".to_string(),
+        MyNodeTemplate::SubtractScalar => "SubtractScalar".to_string(),
+        MyNodeTemplate::MakeVector => "MakeVector".to_string(),
+        MyNodeTemplate::AddVector => "AddVector".to_string(),
+        MyNodeTemplate::SubtractVector => "SubtractVector".to_string(),
+        MyNodeTemplate::VectorTimesScalar => "VectorTimesScalar".to_string(),
+        MyNodeTemplate::Scaffold => "Scaffold".to_string(),
+        MyNodeTemplate::Text => "Text".to_string(),
+        MyNodeTemplate::Dynamic(template) => format!("Dynamic:{}", template.label),
+    }
+}
+
+fn parse_template_tag(tag: &str, known_dynamic: &[Rc<DynamicTemplate>]) -> anyhow::Result<MyNodeTemplate> {
+    match tag {
+        "MakeScalar" => Ok(MyNodeTemplate::MakeScalar),
+        "AddScalar" => Ok(MyNodeTemplate::AddScalar),
+        "SubtractScalar" => Ok(MyNodeTemplate::SubtractScalar),
+        "MakeVector" => Ok(MyNodeTemplate::MakeVector),
+        "AddVector" => Ok(MyNodeTemplate::AddVector),
+        "SubtractVector" => Ok(MyNodeTemplate::SubtractVector),
+        "VectorTimesScalar" => Ok(MyNodeTemplate::VectorTimesScalar),
+        "Scaffold" => Ok(MyNodeTemplate::Scaffold),
+        "Text" => Ok(MyNodeTemplate::Text),
+        _ => {
+            let label = tag
+                .strip_prefix("Dynamic:")
+                .ok_or_else(|| anyhow::anyhow!("unrecognized node template tag '{tag}'"))?;
+            known_dynamic
+                .iter()
+                .find(|template| template.label == label)
+                .map(|template| MyNodeTemplate::Dynamic(template.clone()))
+                .ok_or_else(|| {
+                    anyhow::anyhow!("no known dynamic template labeled '{label}' to match tag '{tag}'")
+                })
+        }
+    }
+}
+
+/// Encodes a single value as `kind:payload`, e.g. `scalar:1.5` or
+/// `vec2:1.5,2.5`. Kept as plain text rather than JSON so a diff of the
+/// exported file reads like a diff of the values actually changed, not like a
+/// diff of JSON punctuation.
+fn encode_value(value: &MyValueType) -> String {
+    match value {
+        MyValueType::Scalar { value } => format!("scalar:{value}"),
+        MyValueType::Vec2 { value } => format!("vec2:{},{}", value.x, value.y),
+        MyValueType::Widget { value } => format!("widget:{value}"),
+        MyValueType::Text { value } => format!("text:{value}"),
+    }
+}
+
+fn decode_value(encoded: &str) -> anyhow::Result<MyValueType> {
+    let (kind, rest) = encoded
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("malformed value '{encoded}': missing ':' separator"))?;
+    match kind {
+        "scalar" => Ok(MyValueType::Scalar {
+            value: rest
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid scalar value '{rest}'"))?,
+        }),
+        "vec2" => {
+            let (x, y) = rest
+                .split_once(',')
+                .ok_or_else(|| anyhow::anyhow!("invalid vec2 value '{rest}': expected 'x,y'"))?;
+            Ok(MyValueType::Vec2 {
+                value: egui::vec2(
+                    x.parse().map_err(|_| anyhow::anyhow!("invalid vec2 x component '{x}'"))?,
+                    y.parse().map_err(|_| anyhow::anyhow!("invalid vec2 y component '{y}'"))?,
+                ),
+            })
+        }
+        "widget" => Ok(MyValueType::Widget {
+            value: serde_json::from_str(rest)
+                .map_err(|err| anyhow::anyhow!("invalid widget JSON '{rest}': {err}"))?,
+        }),
+        "text" => Ok(MyValueType::Text { value: rest.to_string() }),
+        _ => anyhow::bail!("unrecognized value kind '{kind}' in '{encoded}'"),
+    }
+}
+
+/// Escapes `;` and `\` so a `name=value` field survives being joined into the
+/// `;`-separated `values` attribute without being confused for a field
+/// boundary. The surrounding DOT string quoting (see [`quote`]) is a separate
+/// layer applied on top of this one.
+fn escape_field(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == ';' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Splits a `values` attribute back into its `;`-separated fields, undoing
+/// [`escape_field`]'s escaping along the way.
+fn split_fields(s: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            ';' => fields.push(std::mem::take(&mut current)),
+            other => current.push(other),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+/// Wraps `s` in a DOT quoted string, escaping `"` and `\`.
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(graph: &mut MyGraph, user_state: &mut MyGraphState, template: MyNodeTemplate) -> NodeId {
+        graph.add_node(
+            template.node_graph_label(user_state),
+            template.user_data(user_state),
+            |graph, node_id| template.build_node(graph, user_state, node_id),
+        )
+    }
+
+    fn set_value(graph: &mut MyGraph, node_id: NodeId, input_name: &str, value: MyValueType) {
+        let input_id = graph[node_id].get_input(input_name).unwrap();
+        graph.inputs[input_id].value = value;
+    }
+
+    fn connect_by_name(graph: &mut MyGraph, from: NodeId, output: &str, to: NodeId, input: &str) {
+        let output_id = graph[from].get_output(output).unwrap();
+        let input_id = graph[to].get_input(input).unwrap();
+        graph.add_connection(output_id, input_id);
+    }
+
+    #[test]
+    fn round_trip_preserves_nodes_values_and_connections() {
+        let mut graph = MyGraph::default();
+        let mut user_state = MyGraphState::default();
+
+        let a = spawn(&mut graph, &mut user_state, MyNodeTemplate::MakeScalar);
+        set_value(&mut graph, a, "value", MyValueType::Scalar { value: 1.5 });
+        let b = spawn(&mut graph, &mut user_state, MyNodeTemplate::MakeScalar);
+        set_value(&mut graph, b, "value", MyValueType::Scalar { value: 2.5 });
+        let sum = spawn(&mut graph, &mut user_state, MyNodeTemplate::AddScalar);
+        connect_by_name(&mut graph, a, "out", sum, "A");
+        connect_by_name(&mut graph, b, "out", sum, "B");
+
+        let exported = export(&graph);
+        let imported = import(&exported, &[]).expect("round trip should parse what export wrote");
+        let re_exported = export(&imported);
+
+        assert_eq!(exported, re_exported);
+    }
+
+    #[test]
+    fn round_trip_preserves_fields_needing_escaping() {
+        let mut graph = MyGraph::default();
+        let mut user_state = MyGraphState::default();
+
+        let text = spawn(&mut graph, &mut user_state, MyNodeTemplate::Text);
+        set_value(&mut graph, text, "text", MyValueType::Text { value: "a;b\\c\"d".to_string() });
+
+        let exported = export(&graph);
+        let imported = import(&exported, &[]).expect("round trip should parse what export wrote");
+
+        let input_id = imported[text].get_input("text").unwrap();
+        match &imported.inputs[input_id].value {
+            MyValueType::Text { value } => assert_eq!(value, "a;b\\c\"d"),
+            other => panic!("expected Text, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn escape_field_round_trips_through_split_fields() {
+        let fields = vec!["plain".to_string(), "a;b".to_string(), "a\\b".to_string(), "a;b\\c".to_string()];
+        let joined = fields.iter().map(|f| escape_field(f)).collect::<Vec<_>>().join(";");
+
+        assert_eq!(split_fields(&joined), fields);
+    }
+
+    #[test]
+    fn quote_round_trips_through_parse_attrs() {
+        let line = format!("key={}", quote("a \"quoted\" \\ value"));
+        let attrs = parse_attrs(&line);
+
+        assert_eq!(attrs.get("key").map(String::as_str), Some("a \"quoted\" \\ value"));
+    }
+}
+
+/// Parses the contents between `[` and `]` of a node statement into its
+/// `key="value"` attributes, unescaping `\"` and `\\` along the way.
+fn parse_attrs(s: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut chars = s.chars().peekable();
+    loop {
+        while matches!(chars.peek(), Some(c) if c.is_whitespace() || *c == ',') {
+            chars.next();
+        }
+        let mut key = String::new();
+        while matches!(chars.peek(), Some(c) if *c != '=') {
+            key.push(chars.next().expect("peeked Some"));
+        }
+        if chars.next().is_none() {
+            // Ran out of input before finding '=': nothing more to parse.
+            break;
+        }
+        while matches!(chars.peek(), Some(c) if c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.next() != Some('"') {
+            break;
+        }
+        let mut value = String::new();
+        loop {
+            match chars.next() {
+                None | Some('"') => break,
+                Some('\\') => {
+                    if let Some(escaped) = chars.next() {
+                        value.push(escaped);
+                    }
+                }
+                Some(other) => value.push(other),
+            }
+        }
+        attrs.insert(key.trim().to_string(), value);
+    }
+    attrs
+}
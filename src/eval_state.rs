@@ -0,0 +1,98 @@
+//! Persistent, incrementally-recomputed node evaluation state.
+//!
+//! `evaluate_node` used to be handed a throwaway `OutputsCache` every frame,
+//! so the whole dependency subtree of whatever node was active got
+//! recomputed from scratch on every repaint. `EvalState` lives on `NagaApp`
+//! instead, carrying the cache across frames alongside a `dirty` set: a node
+//! is only actually recomputed when it (or something upstream of it) has
+//! changed since it was last evaluated. Edits that invalidate a node's
+//! cached value (an inline param edit, a connection added or removed) mark
+//! it dirty and propagate that forward through the graph's connections, so
+//! everything downstream is recomputed too.
+
+use std::collections::HashMap;
+
+use crate::app::{MyGraph, OutputsCache};
+use crate::graph_ui::id_type::{NodeId, OutputId};
+
+/// Evaluation state that persists across frames: the memoized output cache,
+/// which nodes still need recomputing, and each node's version counter
+/// (bumped every time it's actually recomputed).
+#[derive(Default)]
+pub(crate) struct EvalState {
+    pub(crate) outputs_cache: OutputsCache,
+    dirty: std::collections::HashSet<NodeId>,
+    versions: HashMap<NodeId, u64>,
+}
+
+impl EvalState {
+    pub(crate) fn is_dirty(&self, node_id: NodeId) -> bool {
+        self.dirty.contains(&node_id)
+    }
+
+    pub(crate) fn version(&self, node_id: NodeId) -> u64 {
+        self.versions.get(&node_id).copied().unwrap_or(0)
+    }
+
+    /// Marks `node_id` dirty, then walks forward through `graph`'s
+    /// connections marking every node reachable from it dirty too, since
+    /// their cached outputs were (possibly transitively) computed from
+    /// whatever just changed at `node_id`.
+    pub(crate) fn mark_dirty(&mut self, graph: &MyGraph, node_id: NodeId) {
+        self.mark_many_dirty(graph, [node_id]);
+    }
+
+    /// Like [`EvalState::mark_dirty`], but for several roots at once: the
+    /// reverse-dependency map is built only once and shared across all of
+    /// them, instead of once per root. Use this instead of calling
+    /// `mark_dirty` in a loop.
+    pub(crate) fn mark_many_dirty(
+        &mut self,
+        graph: &MyGraph,
+        node_ids: impl IntoIterator<Item = NodeId>,
+    ) {
+        let reverse_deps = build_reverse_deps(graph);
+        let mut stack: Vec<NodeId> = node_ids.into_iter().collect();
+        while let Some(current) = stack.pop() {
+            if self.dirty.insert(current) {
+                if let Some(downstream) = reverse_deps.get(&current) {
+                    stack.extend(downstream.iter().copied());
+                }
+            }
+        }
+    }
+
+    /// Clears the dirty flag for `node_id` and bumps its version, recording
+    /// that a fresh recomputation just happened.
+    pub(crate) fn mark_clean(&mut self, node_id: NodeId) {
+        self.dirty.remove(&node_id);
+        *self.versions.entry(node_id).or_insert(0) += 1;
+    }
+
+    /// Drops everything this state holds about a node that no longer exists:
+    /// its dirty flag, its version counter, and the cached values of its own
+    /// outputs. Call this when a node is removed from the graph so these
+    /// maps don't grow without bound across repeated add/remove cycles.
+    pub(crate) fn forget_node(&mut self, node_id: NodeId, outputs: impl IntoIterator<Item = OutputId>) {
+        self.dirty.remove(&node_id);
+        self.versions.remove(&node_id);
+        for output_id in outputs {
+            self.outputs_cache.remove(&output_id);
+        }
+    }
+}
+
+/// Maps a node to every node with an input directly wired to one of its
+/// outputs, i.e. the set of things that need re-evaluating if this node's
+/// result changes. Rebuilt from scratch on every edit rather than kept in
+/// sync incrementally, since the graphs this app deals with are small enough
+/// that this is cheap and it rules out an entire class of staleness bugs.
+fn build_reverse_deps(graph: &MyGraph) -> HashMap<NodeId, Vec<NodeId>> {
+    let mut reverse_deps: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    for (input_id, output_id) in graph.iter_connections() {
+        let producer = graph.get_output(output_id).node;
+        let consumer = graph.get_input(input_id).node;
+        reverse_deps.entry(producer).or_default().push(consumer);
+    }
+    reverse_deps
+}
@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+
+use super::egui_compat::egui;
+use egui::{Pos2, Rect, Vec2};
+
+use super::graph::{InputParamKind, Node};
+use super::id_type::{AnyParameterId, InputId, NodeId, OutputId};
+use super::traits::{DataTypeTrait, NodeDataTrait, NodeTemplateIter, NodeTemplateTrait, WidgetValueTrait};
+use super::ui_state::GraphEditorState;
+
+/// Side effects produced while drawing the graph that the library itself
+/// doesn't act on. The host application inspects these after each frame.
+pub enum NodeResponse<UserResponse, NodeData> {
+    ConnectEventStarted(NodeId, AnyParameterId),
+    ConnectEventEnded {
+        output: OutputId,
+        input: InputId,
+    },
+    CreatedNode(NodeId),
+    SelectNode(NodeId),
+    /// The user asked to delete this node. The library has already removed
+    /// it from the graph; `node` and `disconnected` carry everything needed
+    /// to restore it (e.g. for an undo stack).
+    DeleteNodeUi(NodeId),
+    DeleteNodeFull {
+        node_id: NodeId,
+        node: Node<NodeData>,
+        disconnected: Vec<(InputId, OutputId)>,
+    },
+    DisconnectEvent {
+        input: InputId,
+    },
+    RaiseNode(NodeId),
+    MoveNode {
+        node: NodeId,
+        drag_delta: Vec2,
+    },
+    User(UserResponse),
+}
+
+pub struct GraphResponse<UserResponse, NodeData> {
+    pub node_responses: Vec<NodeResponse<UserResponse, NodeData>>,
+    pub cursor_in_editor: bool,
+}
+
+const PORT_RADIUS: f32 = 5.0;
+
+pub fn draw_graph_editor<NodeData, DataType, ValueType, NodeTemplate, UserState>(
+    ui: &mut egui::Ui,
+    state: &mut GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState>,
+    all_kinds: impl NodeTemplateIter<Item = NodeTemplate>,
+    user_state: &mut UserState,
+    mut responses: Vec<NodeResponse<NodeData::Response, NodeData>>,
+) -> GraphResponse<NodeData::Response, NodeData>
+where
+    NodeData: NodeDataTrait<DataType = DataType, ValueType = ValueType, UserState = UserState>,
+    DataType: DataTypeTrait<UserState> + PartialEq + Eq,
+    ValueType:
+        WidgetValueTrait<Response = NodeData::Response, UserState = UserState, NodeData = NodeData>,
+    NodeTemplate: NodeTemplateTrait<
+        NodeData = NodeData,
+        DataType = DataType,
+        ValueType = ValueType,
+        UserState = UserState,
+    >,
+    NodeData::Response: super::traits::UserResponseTrait,
+{
+    let pan = state.pan_zoom.pan;
+    let mut port_locations: HashMap<AnyParameterId, Pos2> = HashMap::new();
+
+    let editor_rect = ui.available_rect_before_wrap();
+    let cursor_in_editor = ui
+        .input(|i| i.pointer.hover_pos())
+        .map(|p| editor_rect.contains(p))
+        .unwrap_or(false);
+
+    for node_id in state.node_order.clone() {
+        if !state.graph.nodes.contains_key(node_id) {
+            continue;
+        }
+        let pos = *state
+            .node_positions
+            .entry(node_id)
+            .expect("node_id is never a null key")
+            .or_insert_with(|| Pos2::new(0.0, 0.0))
+            + pan;
+
+        let is_selected = state.selected_nodes.contains(&node_id);
+        let node_responses = draw_node(
+            ui,
+            &mut state.graph,
+            node_id,
+            pos,
+            is_selected,
+            user_state,
+            &mut port_locations,
+        );
+        for node_response in &node_responses {
+            if let NodeResponse::ConnectEventStarted(_, param) = node_response {
+                state.connection_in_progress = Some(*param);
+            }
+        }
+        responses.extend(node_responses);
+    }
+
+    draw_connections(ui, &state.graph, &port_locations);
+
+    if let Some(start) = state.connection_in_progress {
+        if ui.input(|i| i.pointer.any_released()) {
+            state.connection_in_progress = None;
+            if let Some((input, output)) =
+                find_newly_dropped_connections(ui, &state.graph, &port_locations, start)
+            {
+                responses.push(NodeResponse::ConnectEventEnded { output, input });
+            }
+        }
+    }
+
+    if ui.ui_contains_pointer() {
+        let clicked_at = ui.input(|i| i.pointer.secondary_clicked().then(|| i.pointer.interact_pos()).flatten());
+        if let Some(pos) = clicked_at {
+            state.node_finder = Some(pos);
+        }
+    }
+
+    if let Some(popup_pos) = state.node_finder {
+        let mut keep_open = true;
+        let area_response = egui::Area::new(egui::Id::new("node_finder"))
+            .current_pos(popup_pos)
+            .order(egui::Order::Foreground)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_min_width(140.0);
+                    for template in all_kinds.all_kinds() {
+                        let label = template.node_finder_label(user_state).into_owned();
+                        if ui.button(label).clicked() {
+                            let node_id = state.graph.add_node(
+                                template.node_graph_label(user_state),
+                                template.user_data(user_state),
+                                |graph, node_id| template.build_node(graph, user_state, node_id),
+                            );
+                            state.node_positions.insert(node_id, popup_pos - pan);
+                            state.node_order.push(node_id);
+                            responses.push(NodeResponse::CreatedNode(node_id));
+                            keep_open = false;
+                        }
+                    }
+                });
+            });
+        // A click that lands outside the popup (and didn't just spawn a
+        // node, handled above) dismisses it the same way clicking away from
+        // any other egui popup would.
+        if keep_open && ui.input(|i| i.pointer.any_click()) && !area_response.response.hovered() {
+            keep_open = false;
+        }
+        if !keep_open {
+            state.node_finder = None;
+        }
+    }
+
+    GraphResponse {
+        node_responses: responses,
+        cursor_in_editor,
+    }
+}
+
+fn draw_node<NodeData, DataType, ValueType, UserState>(
+    ui: &mut egui::Ui,
+    graph: &mut super::graph::Graph<NodeData, DataType, ValueType>,
+    node_id: NodeId,
+    screen_pos: Pos2,
+    is_selected: bool,
+    user_state: &mut UserState,
+    port_locations: &mut HashMap<AnyParameterId, Pos2>,
+) -> Vec<NodeResponse<NodeData::Response, NodeData>>
+where
+    NodeData: NodeDataTrait<DataType = DataType, ValueType = ValueType, UserState = UserState>,
+    DataType: DataTypeTrait<UserState> + PartialEq + Eq,
+    ValueType: WidgetValueTrait<Response = NodeData::Response, UserState = UserState, NodeData = NodeData>,
+    NodeData::Response: super::traits::UserResponseTrait,
+{
+    let mut responses = Vec::new();
+    let visuals = ui.visuals().clone();
+    let style = graph.nodes[node_id].user_data.node_style(user_state);
+
+    let background = if is_selected {
+        style
+            .background_selected
+            .unwrap_or(visuals.selection.bg_fill)
+    } else {
+        style.background.unwrap_or(visuals.window_fill)
+    };
+    let titlebar = style.titlebar.unwrap_or(visuals.widgets.open.bg_fill);
+    let titlebar_hovered = style.titlebar_hovered.unwrap_or(titlebar);
+    let rounding = style.corner_rounding.unwrap_or(4.0);
+    let padding = style.padding.unwrap_or(egui::vec2(8.0, 4.0));
+    let border = style.border_thickness.unwrap_or(1.0);
+    let outline = style
+        .outline
+        .unwrap_or(visuals.widgets.noninteractive.bg_stroke.color);
+
+    let area = egui::Area::new(egui::Id::new(("node", node_id)))
+        .current_pos(screen_pos)
+        .order(egui::Order::Middle);
+
+    let area_response = area.show(ui.ctx(), |ui| {
+        egui::Frame::none()
+            .fill(background)
+            .stroke(egui::Stroke::new(border, outline))
+            .rounding(rounding)
+            .inner_margin(padding)
+            .show(ui, |ui| {
+                ui.set_min_width(140.0);
+                // Deferred-paint the titlebar background so its color can
+                // depend on hover, which is only known once the label below
+                // has been laid out (the same trick `egui::Frame` itself
+                // uses internally).
+                let titlebar_bg = ui.painter().add(egui::Shape::Noop);
+                let title_response = ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(graph.nodes[node_id].label.clone()).strong());
+                });
+                let fill = if title_response.response.hovered() {
+                    titlebar_hovered
+                } else {
+                    titlebar
+                };
+                let titlebar_rounding = egui::Rounding {
+                    nw: rounding,
+                    ne: rounding,
+                    sw: 0.0,
+                    se: 0.0,
+                };
+                ui.painter().set(
+                    titlebar_bg,
+                    egui::Shape::rect_filled(
+                        title_response.response.rect,
+                        titlebar_rounding,
+                        fill,
+                    ),
+                );
+
+                if title_response.response.clicked() || title_response.response.drag_started() {
+                    responses.push(NodeResponse::SelectNode(node_id));
+                    responses.push(NodeResponse::RaiseNode(node_id));
+                }
+                if title_response.response.dragged() {
+                    responses.push(NodeResponse::MoveNode {
+                        node: node_id,
+                        drag_delta: title_response.response.drag_delta(),
+                    });
+                }
+
+                ui.separator();
+
+                let outputs = graph.nodes[node_id].outputs.clone();
+                for (name, output_id) in outputs {
+                    let port_response = ui.horizontal(|ui| {
+                        ui.add_space(ui.available_width() - 60.0);
+                        ui.label(name);
+                        let (rect, resp) =
+                            ui.allocate_exact_size(egui::vec2(PORT_RADIUS * 2.0, PORT_RADIUS * 2.0), egui::Sense::click_and_drag());
+                        let color = graph.outputs[output_id].typ.data_type_color(user_state);
+                        ui.painter().circle_filled(rect.center(), PORT_RADIUS, color);
+                        (rect.center(), resp)
+                    });
+                    let (center, resp) = port_response.inner;
+                    port_locations.insert(AnyParameterId::Output(output_id), center);
+                    if resp.drag_started() {
+                        responses.push(NodeResponse::ConnectEventStarted(
+                            node_id,
+                            AnyParameterId::Output(output_id),
+                        ));
+                    }
+                }
+
+                let inputs = graph.nodes[node_id].inputs.clone();
+                for (name, input_id) in inputs {
+                    let kind = graph.inputs[input_id].kind;
+                    let is_connected = graph.connection(input_id).is_some();
+                    let dimmed = (kind == InputParamKind::ConnectionOrConstant && !is_connected
+                        && !graph.inputs[input_id].shown_inline)
+                        || (graph.inputs[input_id].optional && !is_connected);
+
+                    ui.horizontal(|ui| {
+                        let port_color = if is_connected || kind != InputParamKind::ConstantOnly {
+                            graph.inputs[input_id].typ.data_type_color(user_state)
+                        } else {
+                            visuals.widgets.noninteractive.bg_fill
+                        };
+                        let port_color = if dimmed {
+                            port_color.linear_multiply(0.4)
+                        } else {
+                            port_color
+                        };
+                        let (rect, resp) =
+                            ui.allocate_exact_size(egui::vec2(PORT_RADIUS * 2.0, PORT_RADIUS * 2.0), egui::Sense::click_and_drag());
+                        ui.painter().circle_filled(rect.center(), PORT_RADIUS, port_color);
+                        port_locations.insert(AnyParameterId::Input(input_id), rect.center());
+
+                        if resp.drag_started() && is_connected {
+                            // Leave the connection in the graph itself: emitting
+                            // `DisconnectEvent` and letting `do_disconnect` remove it
+                            // (via `apply_node_response`, after this frame's responses
+                            // are drained) is what records the undo entry and marks
+                            // the downstream node dirty. Removing it here directly
+                            // would leave `do_disconnect`'s `remove_connection` finding
+                            // nothing to do, silently dropping both.
+                            if let Some(output) = graph.connection(input_id) {
+                                responses.push(NodeResponse::DisconnectEvent { input: input_id });
+                                responses.push(NodeResponse::ConnectEventStarted(
+                                    node_id,
+                                    AnyParameterId::Output(output),
+                                ));
+                            }
+                        }
+
+                        let show_widget = kind != InputParamKind::ConnectionOnly
+                            && (!is_connected || graph.inputs[input_id].shown_inline);
+                        if show_widget && !is_connected {
+                            let node_data_ptr: *const NodeData = &graph.nodes[node_id].user_data;
+                            // Safety: the value widget only reads `node_data`, and we
+                            // don't hold any other borrow of `graph.nodes[node_id]`
+                            // across this call.
+                            let node_data = unsafe { &*node_data_ptr };
+                            let widget_responses = graph.inputs[input_id].value.value_widget(
+                                &name,
+                                node_id,
+                                ui,
+                                user_state,
+                                node_data,
+                            );
+                            responses.extend(widget_responses.into_iter().map(NodeResponse::User));
+                        } else {
+                            ui.label(name);
+                        }
+                    });
+                }
+
+                ui.separator();
+                let bottom_responses =
+                    graph.nodes[node_id].user_data.bottom_ui(ui, node_id, graph, user_state);
+                responses.extend(bottom_responses);
+            });
+    });
+
+    if area_response.response.clicked() {
+        responses.push(NodeResponse::SelectNode(node_id));
+    }
+
+    responses
+}
+
+fn draw_connections<NodeData, DataType, ValueType>(
+    ui: &mut egui::Ui,
+    graph: &super::graph::Graph<NodeData, DataType, ValueType>,
+    port_locations: &HashMap<AnyParameterId, Pos2>,
+) {
+    let painter = ui.painter();
+    for (input, output) in graph.iter_connections() {
+        if let (Some(&from), Some(&to)) = (
+            port_locations.get(&AnyParameterId::Output(output)),
+            port_locations.get(&AnyParameterId::Input(input)),
+        ) {
+            painter.line_segment([from, to], egui::Stroke::new(2.0, ui.visuals().text_color()));
+        }
+    }
+}
+
+/// How close the pointer needs to land to a candidate port, in screen
+/// pixels, for a released wire to snap to it.
+const SNAP_RADIUS: f32 = PORT_RADIUS * 3.0;
+
+/// Resolves an in-progress wire drag (started from `start`, the far end the
+/// user dragged away from) against the pointer's release position: finds
+/// the nearest port of the opposite kind (input for an output-started drag,
+/// and vice versa) that's within snapping range and actually passes
+/// [`Graph::validate_connection`]. Filtering through the same check the
+/// connection path itself enforces — rather than re-deriving the kind/type
+/// rules here — is what guarantees the wire never visibly snaps to a port
+/// it can't connect to.
+fn find_newly_dropped_connections<NodeData, DataType, ValueType>(
+    ui: &egui::Ui,
+    graph: &super::graph::Graph<NodeData, DataType, ValueType>,
+    port_locations: &HashMap<AnyParameterId, Pos2>,
+    start: AnyParameterId,
+) -> Option<(InputId, OutputId)>
+where
+    DataType: PartialEq,
+{
+    let release_pos = ui.input(|i| i.pointer.interact_pos())?;
+
+    match start {
+        AnyParameterId::Output(output) => {
+            let input = port_locations
+                .iter()
+                .filter_map(|(param, &pos)| match param {
+                    AnyParameterId::Input(input) => Some((*input, pos)),
+                    AnyParameterId::Output(_) => None,
+                })
+                .filter(|(input, _)| graph.validate_connection(output, *input).is_ok())
+                .map(|(input, pos)| (input, pos.distance(release_pos)))
+                .filter(|(_, dist)| *dist <= SNAP_RADIUS)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(input, _)| input)?;
+            Some((input, output))
+        }
+        AnyParameterId::Input(input) => {
+            let output = port_locations
+                .iter()
+                .filter_map(|(param, &pos)| match param {
+                    AnyParameterId::Output(output) => Some((*output, pos)),
+                    AnyParameterId::Input(_) => None,
+                })
+                .filter(|(output, _)| graph.validate_connection(*output, input).is_ok())
+                .map(|(output, pos)| (output, pos.distance(release_pos)))
+                .filter(|(_, dist)| *dist <= SNAP_RADIUS)
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(output, _)| output)?;
+            Some((input, output))
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn rect_from_center(center: Pos2, size: Vec2) -> Rect {
+    Rect::from_center_size(center, size)
+}
@@ -0,0 +1,17 @@
+//! Single point of truth for which copy of the `egui` crate the node-graph
+//! rendering code is built against.
+//!
+//! The desktop app drives the graph through `eframe`'s re-export; the Bevy
+//! plugin (`bevy_plugin.rs`) drives the exact same `MyEditorState` through
+//! `bevy_egui`'s re-export, which is its own pinned version of `egui` and not
+//! guaranteed to be the same crate instance as `eframe`'s. Two different
+//! `egui::Ui` types mean the trait impls shared between both front ends
+//! (`WidgetValueTrait`, `DataTypeTrait`, ...) fail to match at the call site
+//! that isn't selected. Routing every `graph_ui` module's `egui` import
+//! through here and switching on the `bevy` feature keeps the whole node
+//! graph compiling against a single, consistent `egui` for whichever front
+//! end is active.
+#[cfg(feature = "bevy")]
+pub use bevy_egui::egui;
+#[cfg(not(feature = "bevy"))]
+pub use eframe::egui;
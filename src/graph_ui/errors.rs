@@ -7,4 +7,16 @@ pub enum EguiGraphError {
 
     #[error("Parameter {0:?} was not found in the graph.")]
     InvalidParameterId(AnyParameterId),
+
+    #[error("Connecting node {from:?} to node {to:?} would create a cycle")]
+    WouldCreateCycle { from: NodeId, to: NodeId },
+
+    #[error("Parameter {output:?} and {input:?} have incompatible types")]
+    IncompatibleTypes {
+        output: AnyParameterId,
+        input: AnyParameterId,
+    },
+
+    #[error("Port {0:?} doesn't accept connections")]
+    PortRejectsConnection(AnyParameterId),
 }
\ No newline at end of file
@@ -0,0 +1,415 @@
+use slotmap::{SecondaryMap, SlotMap};
+
+use super::errors::EguiGraphError;
+use super::id_type::{AnyParameterId, InputId, NodeId, OutputId};
+
+/// Tells the graph how an input parameter may be filled in: from an inline
+/// constant widget, from an incoming connection, or both.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputParamKind {
+    ConstantOnly,
+    ConnectionOnly,
+    ConnectionOrConstant,
+}
+
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node<NodeData> {
+    pub id: NodeId,
+    pub label: String,
+    pub inputs: Vec<(String, InputId)>,
+    pub outputs: Vec<(String, OutputId)>,
+    pub user_data: NodeData,
+}
+
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputParam<DataType, ValueType> {
+    pub typ: DataType,
+    pub value: ValueType,
+    pub kind: InputParamKind,
+    pub node: NodeId,
+    /// When true, the constant-value widget is drawn inline in the node even
+    /// if the input is connected. When false (the default for plain
+    /// `ConnectionOnly` inputs) the widget is hidden entirely.
+    pub shown_inline: bool,
+    /// When true, this input is allowed to be left disconnected with no
+    /// constant fallback: evaluation should treat it as absent rather than
+    /// require a wire. See [`Graph::is_input_satisfied`].
+    pub optional: bool,
+}
+
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputParam<DataType> {
+    pub typ: DataType,
+    pub node: NodeId,
+}
+
+/// The node graph. Nodes are stored with stable ids in a slotmap so UI state
+/// referring to them (positions, selection, order) survives mutation.
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Graph<NodeData, DataType, ValueType> {
+    pub nodes: SlotMap<NodeId, Node<NodeData>>,
+    pub inputs: SlotMap<InputId, InputParam<DataType, ValueType>>,
+    pub outputs: SlotMap<OutputId, OutputParam<DataType>>,
+    /// Maps an input to the output that feeds it, if connected.
+    pub connections: SecondaryMap<InputId, OutputId>,
+}
+
+impl<NodeData, DataType, ValueType> Default for Graph<NodeData, DataType, ValueType> {
+    fn default() -> Self {
+        Self {
+            nodes: SlotMap::default(),
+            inputs: SlotMap::default(),
+            outputs: SlotMap::default(),
+            connections: SecondaryMap::default(),
+        }
+    }
+}
+
+impl<NodeData> Node<NodeData> {
+    pub fn get_input(&self, name: &str) -> Result<InputId, EguiGraphError> {
+        self.inputs
+            .iter()
+            .find(|(param_name, _)| param_name == name)
+            .map(|x| x.1)
+            .ok_or_else(|| EguiGraphError::NoParameterNamed(self.id, name.to_string()))
+    }
+
+    pub fn get_output(&self, name: &str) -> Result<OutputId, EguiGraphError> {
+        self.outputs
+            .iter()
+            .find(|(param_name, _)| param_name == name)
+            .map(|x| x.1)
+            .ok_or_else(|| EguiGraphError::NoParameterNamed(self.id, name.to_string()))
+    }
+}
+
+impl<NodeData, DataType, ValueType> Graph<NodeData, DataType, ValueType> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(
+        &mut self,
+        label: String,
+        user_data: NodeData,
+        f: impl FnOnce(&mut Graph<NodeData, DataType, ValueType>, NodeId),
+    ) -> NodeId {
+        let node_id = self.nodes.insert_with_key(|node_id| Node {
+            id: node_id,
+            label,
+            user_data,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        });
+        f(self, node_id);
+        node_id
+    }
+
+    pub fn add_input_param(
+        &mut self,
+        node_id: NodeId,
+        name: String,
+        typ: DataType,
+        value: ValueType,
+        kind: InputParamKind,
+        shown_inline: bool,
+    ) -> InputId {
+        let input_id = self.inputs.insert(InputParam {
+            typ,
+            value,
+            kind,
+            node: node_id,
+            shown_inline,
+            optional: false,
+        });
+        self.nodes[node_id].inputs.push((name, input_id));
+        input_id
+    }
+
+    /// Like [`Graph::add_input_param`], but the resulting input is allowed to
+    /// stay disconnected: [`Graph::is_input_satisfied`] treats it as
+    /// satisfied even when empty, and evaluation can tell "no connection"
+    /// apart from "connected to a real value" instead of silently using
+    /// `value` as a fallback constant.
+    pub fn add_optional_input_param(
+        &mut self,
+        node_id: NodeId,
+        name: String,
+        typ: DataType,
+        value: ValueType,
+        kind: InputParamKind,
+        shown_inline: bool,
+    ) -> InputId {
+        let input_id = self.add_input_param(node_id, name, typ, value, kind, shown_inline);
+        self.inputs[input_id].optional = true;
+        input_id
+    }
+
+    pub fn add_output_param(&mut self, node_id: NodeId, name: String, typ: DataType) -> OutputId {
+        let output_id = self.outputs.insert(OutputParam { typ, node: node_id });
+        self.nodes[node_id].outputs.push((name, output_id));
+        output_id
+    }
+
+    /// Removes a node and every connection touching it. Returns the removed
+    /// node data together with the connections that were severed, as
+    /// `(input, output)` pairs, so callers (e.g. undo history) can restore
+    /// them exactly.
+    pub fn remove_node(
+        &mut self,
+        node_id: NodeId,
+    ) -> (Node<NodeData>, Vec<(InputId, OutputId)>) {
+        let mut disconnected = Vec::new();
+
+        for (_, input) in &self.nodes[node_id].inputs {
+            if let Some(output) = self.connections.remove(*input) {
+                disconnected.push((*input, output));
+            }
+        }
+        for (_, output) in &self.nodes[node_id].outputs {
+            let affected_inputs = self
+                .connections
+                .iter()
+                .filter(|(_, out)| *out == output)
+                .map(|(input, _)| input)
+                .collect::<Vec<_>>();
+            for input in affected_inputs {
+                self.connections.remove(input);
+                disconnected.push((input, *output));
+            }
+        }
+
+        for (_, input) in self.nodes[node_id].inputs.clone() {
+            self.inputs.remove(input);
+        }
+        for (_, output) in self.nodes[node_id].outputs.clone() {
+            self.outputs.remove(output);
+        }
+        let node = self.nodes.remove(node_id).expect("Node should exist");
+
+        (node, disconnected)
+    }
+
+    pub fn remove_connection(&mut self, input_id: InputId) -> Option<OutputId> {
+        self.connections.remove(input_id)
+    }
+
+    pub fn add_connection(&mut self, output: OutputId, input: InputId) {
+        self.connections.insert(input, output);
+    }
+
+    /// Checks that connecting `output` to `input` is legal — same data type
+    /// on both ends, and doesn't create a cycle — without mutating the
+    /// graph. Callers that accept connections from user input (as opposed to
+    /// e.g. undo/redo or deserialization, which only ever replay connections
+    /// that were already validated once) should call this before
+    /// [`Graph::add_connection`].
+    pub fn validate_connection(
+        &self,
+        output: OutputId,
+        input: InputId,
+    ) -> Result<(), EguiGraphError>
+    where
+        DataType: PartialEq,
+    {
+        if self.inputs[input].kind == InputParamKind::ConstantOnly {
+            return Err(EguiGraphError::PortRejectsConnection(AnyParameterId::Input(input)));
+        }
+
+        if self.outputs[output].typ != self.inputs[input].typ {
+            return Err(EguiGraphError::IncompatibleTypes {
+                output: AnyParameterId::Output(output),
+                input: AnyParameterId::Input(input),
+            });
+        }
+
+        let from = self.outputs[output].node;
+        let to = self.inputs[input].node;
+        if self.would_create_cycle(from, to) {
+            return Err(EguiGraphError::WouldCreateCycle { from, to });
+        }
+
+        Ok(())
+    }
+
+    /// Whether a connection from `from` to `to` would create a cycle: true
+    /// if `to` can already reach `from` by following existing connections
+    /// forward (a node's outputs, to whatever inputs they feed, to *their*
+    /// node's outputs, and so on).
+    fn would_create_cycle(&self, from: NodeId, to: NodeId) -> bool {
+        let mut visited = std::collections::HashSet::new();
+        let mut stack = vec![to];
+        while let Some(node) = stack.pop() {
+            if node == from {
+                return true;
+            }
+            if !visited.insert(node) {
+                continue;
+            }
+            for (_, output) in &self.nodes[node].outputs {
+                for (input, connected_output) in self.connections.iter() {
+                    if connected_output == output {
+                        stack.push(self.inputs[input].node);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    pub fn connection(&self, input: InputId) -> Option<OutputId> {
+        self.connections.get(input).copied()
+    }
+
+    /// Whether `input_id` is in a valid state to evaluate from: it's marked
+    /// `optional` (so being empty is fine), it accepts a constant value (so
+    /// it always has *some* value), or it's actually connected. Only a
+    /// required, connection-only input that's been left disconnected is
+    /// unsatisfied. Intended for validating a node before evaluating it.
+    pub fn is_input_satisfied(&self, input_id: InputId) -> bool {
+        let input = &self.inputs[input_id];
+        input.optional
+            || input.kind != InputParamKind::ConnectionOnly
+            || self.connection(input_id).is_some()
+    }
+
+    pub fn iter_nodes(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.keys()
+    }
+
+    pub fn iter_connections(&self) -> impl Iterator<Item = (InputId, OutputId)> + '_ {
+        self.connections.iter().map(|(i, o)| (i, *o))
+    }
+
+    pub fn any_param_type(&self, param: AnyParameterId) -> Result<&DataType, EguiGraphError> {
+        match param {
+            AnyParameterId::Input(input) => self
+                .inputs
+                .get(input)
+                .map(|x| &x.typ)
+                .ok_or(EguiGraphError::InvalidParameterId(param)),
+            AnyParameterId::Output(output) => self
+                .outputs
+                .get(output)
+                .map(|x| &x.typ)
+                .ok_or(EguiGraphError::InvalidParameterId(param)),
+        }
+    }
+
+    pub fn get_input(&self, input: InputId) -> &InputParam<DataType, ValueType> {
+        &self.inputs[input]
+    }
+
+    pub fn get_output(&self, output: OutputId) -> &OutputParam<DataType> {
+        &self.outputs[output]
+    }
+}
+
+impl<NodeData, DataType, ValueType> std::ops::Index<NodeId>
+    for Graph<NodeData, DataType, ValueType>
+{
+    type Output = Node<NodeData>;
+    fn index(&self, index: NodeId) -> &Self::Output {
+        &self.nodes[index]
+    }
+}
+
+impl<NodeData, DataType, ValueType> std::ops::IndexMut<NodeId>
+    for Graph<NodeData, DataType, ValueType>
+{
+    fn index_mut(&mut self, index: NodeId) -> &mut Self::Output {
+        &mut self.nodes[index]
+    }
+}
+
+impl<NodeData, DataType, ValueType> std::ops::Index<OutputId>
+    for Graph<NodeData, DataType, ValueType>
+{
+    type Output = OutputParam<DataType>;
+    fn index(&self, index: OutputId) -> &Self::Output {
+        &self.outputs[index]
+    }
+}
+
+impl<NodeData, DataType, ValueType> std::ops::Index<InputId>
+    for Graph<NodeData, DataType, ValueType>
+{
+    type Output = InputParam<DataType, ValueType>;
+    fn index(&self, index: InputId) -> &Self::Output {
+        &self.inputs[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestGraph = Graph<(), i32, i32>;
+
+    fn add_node_with_port(graph: &mut TestGraph) -> (NodeId, InputId, OutputId) {
+        let node_id = graph.add_node("node".to_string(), (), |_, _| {});
+        let input = graph.add_input_param(
+            node_id,
+            "in".to_string(),
+            0,
+            0,
+            InputParamKind::ConnectionOnly,
+            false,
+        );
+        let output = graph.add_output_param(node_id, "out".to_string(), 0);
+        (node_id, input, output)
+    }
+
+    #[test]
+    fn validate_connection_allows_acyclic_chain() {
+        let mut graph = TestGraph::default();
+        let (_, _, a_out) = add_node_with_port(&mut graph);
+        let (_, b_in, _) = add_node_with_port(&mut graph);
+
+        assert!(graph.validate_connection(a_out, b_in).is_ok());
+    }
+
+    #[test]
+    fn validate_connection_rejects_direct_cycle() {
+        let mut graph = TestGraph::default();
+        let (_, a_in, a_out) = add_node_with_port(&mut graph);
+        let (_, b_in, b_out) = add_node_with_port(&mut graph);
+
+        graph.add_connection(a_out, b_in);
+
+        // Wiring b's output back into a's input would close the loop a -> b -> a.
+        assert!(matches!(
+            graph.validate_connection(b_out, a_in),
+            Err(EguiGraphError::WouldCreateCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_connection_rejects_self_loop() {
+        let mut graph = TestGraph::default();
+        let (_, a_in, a_out) = add_node_with_port(&mut graph);
+
+        assert!(matches!(
+            graph.validate_connection(a_out, a_in),
+            Err(EguiGraphError::WouldCreateCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn validate_connection_rejects_longer_cycle() {
+        let mut graph = TestGraph::default();
+        let (_, a_in, a_out) = add_node_with_port(&mut graph);
+        let (_, b_in, b_out) = add_node_with_port(&mut graph);
+        let (_, c_in, c_out) = add_node_with_port(&mut graph);
+
+        graph.add_connection(a_out, b_in);
+        graph.add_connection(b_out, c_in);
+
+        // a -> b -> c already exists; c -> a would close a three-node loop.
+        assert!(matches!(
+            graph.validate_connection(c_out, a_in),
+            Err(EguiGraphError::WouldCreateCycle { .. })
+        ));
+    }
+}
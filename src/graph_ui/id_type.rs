@@ -0,0 +1,57 @@
+use slotmap::new_key_type;
+
+new_key_type! { pub struct NodeId; }
+new_key_type! { pub struct InputId; }
+new_key_type! { pub struct OutputId; }
+
+/// An identifier for a graph parameter that could be either an input or an
+/// output. Useful when code needs to treat both uniformly, e.g. when storing
+/// the two ends of a connection being dragged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnyParameterId {
+    Input(InputId),
+    Output(OutputId),
+}
+
+impl AnyParameterId {
+    pub fn assume_input(&self) -> InputId {
+        match self {
+            AnyParameterId::Input(input) => *input,
+            AnyParameterId::Output(_) => panic!("Not an InputId"),
+        }
+    }
+
+    pub fn assume_output(&self) -> OutputId {
+        match self {
+            AnyParameterId::Output(output) => *output,
+            AnyParameterId::Input(_) => panic!("Not an OutputId"),
+        }
+    }
+
+    pub fn as_input(&self) -> Option<InputId> {
+        match self {
+            AnyParameterId::Input(input) => Some(*input),
+            AnyParameterId::Output(_) => None,
+        }
+    }
+
+    pub fn as_output(&self) -> Option<OutputId> {
+        match self {
+            AnyParameterId::Output(output) => Some(*output),
+            AnyParameterId::Input(_) => None,
+        }
+    }
+}
+
+impl From<OutputId> for AnyParameterId {
+    fn from(output: OutputId) -> Self {
+        Self::Output(output)
+    }
+}
+
+impl From<InputId> for AnyParameterId {
+    fn from(input: InputId) -> Self {
+        Self::Input(input)
+    }
+}
@@ -0,0 +1,7 @@
+pub mod editor_ui;
+pub mod egui_compat;
+pub mod errors;
+pub mod graph;
+pub mod id_type;
+pub mod traits;
+pub mod ui_state;
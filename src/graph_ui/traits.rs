@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+use super::egui_compat::egui;
+
+use super::editor_ui::NodeResponse;
+use super::graph::Graph;
+use super::id_type::NodeId;
+
+/// Tells the graph UI how to color and label a data type.
+pub trait DataTypeTrait<UserState>: PartialEq + Eq {
+    fn data_type_color(&self, user_state: &mut UserState) -> egui::Color32;
+    fn name(&self) -> Cow<'_, str>;
+}
+
+/// Tells the graph UI how to turn a node template into an actual node.
+pub trait NodeTemplateTrait: Clone {
+    type NodeData: NodeDataTrait;
+    type DataType: DataTypeTrait<Self::UserState>;
+    type ValueType: WidgetValueTrait;
+    type UserState;
+    type CategoryType: std::fmt::Display;
+
+    fn node_finder_label(&self, user_state: &mut Self::UserState) -> Cow<'_, str>;
+    fn node_graph_label(&self, user_state: &mut Self::UserState) -> String;
+    fn node_finder_categories(&self, user_state: &mut Self::UserState) -> Vec<Self::CategoryType>;
+    fn user_data(&self, user_state: &mut Self::UserState) -> Self::NodeData;
+    fn build_node(
+        &self,
+        graph: &mut Graph<Self::NodeData, Self::DataType, Self::ValueType>,
+        user_state: &mut Self::UserState,
+        node_id: NodeId,
+    );
+}
+
+/// Enumerates the templates that should show up in the node finder popup.
+pub trait NodeTemplateIter {
+    type Item;
+    fn all_kinds(&self) -> Vec<Self::Item>;
+}
+
+/// Tells the graph UI which widget to draw for an inline constant value.
+pub trait WidgetValueTrait {
+    type Response;
+    type UserState;
+    type NodeData;
+
+    fn value_widget(
+        &mut self,
+        param_name: &str,
+        node_id: NodeId,
+        ui: &mut egui::Ui,
+        user_state: &mut Self::UserState,
+        node_data: &Self::NodeData,
+    ) -> Vec<Self::Response>;
+}
+
+/// Marker trait for the user-defined response enum threaded through node
+/// drawing side effects.
+pub trait UserResponseTrait: Clone + std::fmt::Debug + PartialEq + Eq {}
+
+/// Per-node paint overrides for the node frame. Every field is optional;
+/// `None` falls back to the current egui visuals, so most node kinds can
+/// leave this at its default and only override what sets them apart (e.g.
+/// giving a whole category of nodes a distinct titlebar color).
+#[derive(Clone, Copy, Default)]
+pub struct NodeStyle {
+    pub background: Option<egui::Color32>,
+    pub titlebar: Option<egui::Color32>,
+    pub titlebar_hovered: Option<egui::Color32>,
+    pub background_selected: Option<egui::Color32>,
+    pub outline: Option<egui::Color32>,
+    pub corner_rounding: Option<f32>,
+    pub padding: Option<egui::Vec2>,
+    pub border_thickness: Option<f32>,
+}
+
+/// Tells the graph UI how to draw the extra per-node UI.
+pub trait NodeDataTrait
+where
+    Self: Sized,
+{
+    type Response: UserResponseTrait;
+    type UserState;
+    type DataType: DataTypeTrait<Self::UserState>;
+    type ValueType: WidgetValueTrait;
+
+    fn bottom_ui(
+        &self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        user_state: &mut Self::UserState,
+    ) -> Vec<NodeResponse<Self::Response, Self>>
+    where
+        Self::Response: UserResponseTrait;
+
+    /// Paint overrides for this node. Defaults to all-`None`, i.e. the
+    /// standard egui-visuals look used by every node that doesn't care.
+    fn node_style(&self, _user_state: &mut Self::UserState) -> NodeStyle {
+        NodeStyle::default()
+    }
+}
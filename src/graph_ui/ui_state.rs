@@ -0,0 +1,91 @@
+use slotmap::SecondaryMap;
+
+use super::egui_compat::egui;
+use egui::Pos2;
+
+use super::editor_ui::{self, GraphResponse};
+use super::graph::Graph;
+use super::id_type::{AnyParameterId, NodeId};
+use super::traits::{NodeDataTrait, NodeTemplateIter, NodeTemplateTrait, WidgetValueTrait};
+
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct PanZoom {
+    pub pan: egui::Vec2,
+    pub zoom: f32,
+}
+
+impl Default for PanZoom {
+    fn default() -> Self {
+        Self {
+            pan: egui::Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+/// Top-level state for the graph editor widget. Everything here is UI state;
+/// the graph topology itself lives in `graph`.
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState> {
+    pub graph: Graph<NodeData, DataType, ValueType>,
+    pub node_positions: SecondaryMap<NodeId, Pos2>,
+    pub node_order: Vec<NodeId>,
+    pub selected_nodes: Vec<NodeId>,
+    pub pan_zoom: PanZoom,
+    /// The far end of an in-progress wire drag, i.e. the port the user is
+    /// dragging *from* (see `editor_ui::draw_graph_editor`'s release-time
+    /// port search). Purely transient UI state, so it's never persisted.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub connection_in_progress: Option<AnyParameterId>,
+    /// Screen position the node finder popup should be drawn at, or `None`
+    /// when it's closed. Opened by right-clicking empty editor space; see
+    /// `editor_ui::draw_graph_editor`.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub node_finder: Option<Pos2>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub _marker: std::marker::PhantomData<(NodeTemplate, UserState)>,
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState> Default
+    for GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState>
+{
+    fn default() -> Self {
+        Self {
+            graph: Graph::default(),
+            node_positions: SecondaryMap::default(),
+            node_order: Vec::new(),
+            selected_nodes: Vec::new(),
+            pan_zoom: PanZoom::default(),
+            connection_in_progress: None,
+            node_finder: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<NodeData, DataType, ValueType, NodeTemplate, UserState>
+    GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserState>
+where
+    NodeData: NodeDataTrait<DataType = DataType, ValueType = ValueType, UserState = UserState>,
+    DataType: super::traits::DataTypeTrait<UserState> + PartialEq + Eq,
+    ValueType:
+        WidgetValueTrait<Response = NodeData::Response, UserState = UserState, NodeData = NodeData>,
+    NodeTemplate: NodeTemplateTrait<
+        NodeData = NodeData,
+        DataType = DataType,
+        ValueType = ValueType,
+        UserState = UserState,
+    >,
+    NodeData::Response: super::traits::UserResponseTrait,
+{
+    pub fn draw_graph_editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        all_kinds: impl NodeTemplateIter<Item = NodeTemplate>,
+        user_state: &mut UserState,
+        extra_responses: Vec<editor_ui::NodeResponse<NodeData::Response, NodeData>>,
+    ) -> GraphResponse<NodeData::Response, NodeData> {
+        editor_ui::draw_graph_editor(ui, self, all_kinds, user_state, extra_responses)
+    }
+}
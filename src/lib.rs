@@ -0,0 +1,15 @@
+//! Library surface for embedding this crate's node graph editor in a host
+//! application. `main.rs`'s `eframe` binary is one consumer of these same
+//! modules; [`bevy_plugin`] is the other — a host Bevy app embeds
+//! [`bevy_plugin::NodeGraphPlugin`] directly rather than reimplementing the
+//! event loop `main.rs` drives.
+
+pub mod app;
+#[cfg(feature = "bevy")]
+pub mod bevy_plugin;
+mod commands;
+mod dot;
+mod eval_state;
+pub mod graph_ui;
+mod profiler;
+mod widget_spec;
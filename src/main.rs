@@ -0,0 +1,15 @@
+#[cfg(not(feature = "bevy"))]
+fn main() -> eframe::Result<()> {
+    let native_options = eframe::NativeOptions::default();
+    eframe::run_native(
+        "Naga",
+        native_options,
+        Box::new(|cc| Box::new(naga::app::NagaApp::new(cc))),
+    )
+}
+
+/// Under the `bevy` feature this crate is built as a library for a host Bevy
+/// app to embed via `bevy_plugin::NodeGraphPlugin` — see that module's doc
+/// comment — so there's no standalone binary to run.
+#[cfg(feature = "bevy")]
+fn main() {}
@@ -0,0 +1,101 @@
+//! Opt-in per-node evaluation profiler.
+//!
+//! Wraps the `evaluate_node`/`evaluate_input` call chain to record, per
+//! `NodeId`, the wall-clock time spent in that node's own computation
+//! (excluding time spent evaluating its dependencies), how many times it was
+//! actually recomputed vs. served from the cache, and its invocation count.
+//! Disabled by default so the normal evaluation path stays allocation-free;
+//! every recording method is a no-op unless [`Profiler::set_enabled`] has
+//! been called. Counters are cleared at the start of each evaluation root
+//! (see [`Profiler::reset`]) so they reflect a single pass rather than
+//! cumulative history across frames.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::graph_ui::id_type::NodeId;
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct NodeStats {
+    pub(crate) self_time: Duration,
+    /// Total times this node was visited, whether served from cache or
+    /// actually recomputed: `cache_hits + cache_misses`.
+    pub(crate) invocations: u32,
+    pub(crate) cache_hits: u32,
+    pub(crate) cache_misses: u32,
+}
+
+#[derive(Default)]
+pub(crate) struct Profiler {
+    enabled: bool,
+    stats: HashMap<NodeId, NodeStats>,
+    /// One entry per `evaluate_node` call currently on the stack (innermost
+    /// last): its start time, and the time already charged to its children.
+    /// Used to subtract children's time out of a node's own elapsed time.
+    frames: Vec<(Instant, Duration)>,
+}
+
+impl Profiler {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub(crate) fn stats(&self) -> impl Iterator<Item = (NodeId, NodeStats)> + '_ {
+        self.stats.iter().map(|(id, stats)| (*id, *stats))
+    }
+
+    /// Clears accumulated stats. Call this before starting a fresh
+    /// evaluation root so timings reflect a single pass rather than piling up
+    /// across repaints.
+    pub(crate) fn reset(&mut self) {
+        self.stats.clear();
+        self.frames.clear();
+    }
+
+    /// Records that `node_id`'s cached result was reused instead of being
+    /// recomputed.
+    pub(crate) fn record_cache_hit(&mut self, node_id: NodeId) {
+        if !self.enabled {
+            return;
+        }
+        let stats = self.stats.entry(node_id).or_default();
+        stats.invocations += 1;
+        stats.cache_hits += 1;
+    }
+
+    /// Call immediately before actually recomputing `node_id` (i.e. right
+    /// before entering its template match arm). Pairs with [`Self::end_node`].
+    pub(crate) fn begin_node(&mut self, node_id: NodeId) {
+        if !self.enabled {
+            return;
+        }
+        let stats = self.stats.entry(node_id).or_default();
+        stats.invocations += 1;
+        stats.cache_misses += 1;
+        self.frames.push((Instant::now(), Duration::ZERO));
+    }
+
+    /// Call right after `node_id` finishes recomputing. Pairs with
+    /// [`Self::begin_node`]; charges this node's own elapsed time (its total
+    /// time minus whatever its dependencies already billed to it) to its
+    /// stats, then folds its total time into its parent frame's child time so
+    /// the parent's own self-time excludes it in turn.
+    pub(crate) fn end_node(&mut self, node_id: NodeId) {
+        if !self.enabled {
+            return;
+        }
+        let Some((start, child_time)) = self.frames.pop() else {
+            return;
+        };
+        let total = start.elapsed();
+        let stats = self.stats.entry(node_id).or_default();
+        stats.self_time += total.saturating_sub(child_time);
+        if let Some((_, parent_child_time)) = self.frames.last_mut() {
+            *parent_child_time += total;
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Declarative rendering for the `Widget` value type.
+//!
+//! `MyValueType::Widget` stores a `serde_json::Value`; rather than having
+//! `value_widget` special-case every shape a node might want to compose
+//! (see the `Scaffold` node's `body`/`header` inputs), the value is treated
+//! as a small widget spec: `{"kind": "slider", ...}`, `{"kind": "checkbox",
+//! ...}`, `{"kind": "combo", ...}`, or `{"kind": "vstack", "children": [...]}`
+//! for nesting. `draw_widget_spec` interprets the spec, draws the matching
+//! egui control, and writes any edit back into the same `Value` in place so
+//! it round-trips through the node graph's existing serde persistence.
+
+use serde_json::Value;
+
+use crate::graph_ui::egui_compat::egui;
+
+/// Draws the widget described by `spec` and writes back whatever the user
+/// edits. A spec with a missing or unrecognized `kind` falls back to a label
+/// showing the raw JSON, so a bad spec is visible instead of silently blank.
+pub fn draw_widget_spec(ui: &mut egui::Ui, spec: &mut Value) {
+    match spec.get("kind").and_then(Value::as_str) {
+        Some("slider") => draw_slider(ui, spec),
+        Some("checkbox") => draw_checkbox(ui, spec),
+        Some("combo") => draw_combo(ui, spec),
+        Some("vstack") => draw_vstack(ui, spec),
+        _ => {
+            ui.label(format!("(unsupported widget spec: {spec})"));
+        }
+    }
+}
+
+fn draw_slider(ui: &mut egui::Ui, spec: &mut Value) {
+    let min = spec.get("min").and_then(Value::as_f64).unwrap_or(0.0);
+    let max = spec.get("max").and_then(Value::as_f64).unwrap_or(1.0);
+    let mut value = spec.get("value").and_then(Value::as_f64).unwrap_or(min);
+    if ui.add(egui::Slider::new(&mut value, min..=max)).changed() {
+        spec["value"] = Value::from(value);
+    }
+}
+
+fn draw_checkbox(ui: &mut egui::Ui, spec: &mut Value) {
+    let mut value = spec.get("value").and_then(Value::as_bool).unwrap_or(false);
+    if ui.checkbox(&mut value, "").changed() {
+        spec["value"] = Value::from(value);
+    }
+}
+
+fn draw_combo(ui: &mut egui::Ui, spec: &mut Value) {
+    // Kept index-aligned with the original JSON array (rather than dropping
+    // non-string entries) since `selected` is an index into it.
+    let options: Vec<String> = spec
+        .get("options")
+        .and_then(Value::as_array)
+        .map(|options| {
+            options
+                .iter()
+                .map(|option| option.as_str().unwrap_or_default().to_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut selected = spec.get("selected").and_then(Value::as_u64).unwrap_or(0) as usize;
+    let selected_text = options.get(selected).cloned().unwrap_or_default();
+
+    egui::ComboBox::from_id_source(ui.id().with("widget_spec_combo"))
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            for (index, option) in options.iter().enumerate() {
+                if ui.selectable_label(index == selected, option).clicked() {
+                    selected = index;
+                }
+            }
+        });
+    spec["selected"] = Value::from(selected as u64);
+}
+
+fn draw_vstack(ui: &mut egui::Ui, spec: &mut Value) {
+    let Some(children) = spec.get_mut("children").and_then(Value::as_array_mut) else {
+        return;
+    };
+    ui.vertical(|ui| {
+        for (index, child) in children.iter_mut().enumerate() {
+            ui.push_id(index, |ui| draw_widget_spec(ui, child));
+        }
+    });
+}